@@ -0,0 +1 @@
+DELETE FROM channel_filter WHERE guild_id = $1 AND channel_id = $2;
\ No newline at end of file
@@ -0,0 +1 @@
+DELETE FROM schedule_entry WHERE guild_id = $1 AND entry_id = $2;
\ No newline at end of file
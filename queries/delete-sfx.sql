@@ -0,0 +1 @@
+DELETE FROM guild_sfx WHERE guild_id = $1 AND name = $2;
\ No newline at end of file
@@ -54,6 +54,9 @@ CREATE TABLE IF NOT EXISTS join_config(
 	channel BIGINT
 );
 
+/* voicehunt::mode::Join::Follow */
+ALTER TABLE join_config ADD COLUMN IF NOT EXISTS user_id BIGINT;
+
 /* map with Enum: should be server::Label */
 CREATE TABLE IF NOT EXISTS server_type(
 	guild_id BIGINT PRIMARY KEY NOT NULL,
@@ -67,4 +70,117 @@ CREATE TABLE IF NOT EXISTS opt_in_out(
 	role_id BIGINT
 );
 
+/* config::ConsentNotice */
+ALTER TABLE opt_in_out ADD COLUMN IF NOT EXISTS notice_channel BIGINT;
+ALTER TABLE opt_in_out ADD COLUMN IF NOT EXISTS notice_template TEXT;
+
+/* Encoded trace bodies, used by trace_sink::PostgresSink */
+CREATE TABLE IF NOT EXISTS trace_store(
+	trace_key TEXT PRIMARY KEY NOT NULL,
+	data BYTEA NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+/* One row per stored trace, for curation. Holds no guild or user IDs, to keep traces anonymous. */
+CREATE TABLE IF NOT EXISTS trace_manifest(
+	trace_key TEXT PRIMARY KEY NOT NULL,
+	label INTEGER NOT NULL,
+	length_ns BIGINT NOT NULL,
+	total_user_count BIGINT NOT NULL,
+	starting_user_count BIGINT NOT NULL,
+	optout_user_count BIGINT NOT NULL,
+	server TEXT,
+	region TEXT,
+	trace_version INTEGER NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+/* Name of a soundscape pack in the soundscape directory */
+CREATE TABLE IF NOT EXISTS soundscape_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	name TEXT NOT NULL
+);
+
+/* Sound effects uploaded by guild admins, stored under the custom SFX directory */
+CREATE TABLE IF NOT EXISTS guild_sfx(
+	guild_id BIGINT NOT NULL,
+	name TEXT NOT NULL,
+	file_path TEXT NOT NULL,
+	duration_ms BIGINT NOT NULL,
+	size_bytes BIGINT NOT NULL,
+	uploader BIGINT NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	PRIMARY KEY (guild_id, name)
+);
+
+/* config::ChannelFilter: channels to (only) hunt in, or to avoid */
+CREATE TABLE IF NOT EXISTS channel_filter(
+	guild_id BIGINT NOT NULL,
+	channel_id BIGINT NOT NULL,
+	allow BOOLEAN NOT NULL,
+	PRIMARY KEY (guild_id, channel_id)
+);
+
+/* config::PopulationRules */
+CREATE TABLE IF NOT EXISTS population_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	ignore_bots BOOLEAN NOT NULL,
+	ignore_deafened BOOLEAN NOT NULL,
+	ignore_muted BOOLEAN NOT NULL,
+	min_users INTEGER NOT NULL
+);
+
+/* config::SwitchRules */
+CREATE TABLE IF NOT EXISTS switch_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	dwell_secs INTEGER NOT NULL,
+	margin INTEGER NOT NULL,
+	debounce_secs INTEGER NOT NULL
+);
+
+/* config::ReactionRules */
+CREATE TABLE IF NOT EXISTS reaction_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	on_join BOOLEAN NOT NULL,
+	on_leave BOOLEAN NOT NULL,
+	cooldown_secs INTEGER NOT NULL
+);
+
+/* config::DuckRules */
+CREATE TABLE IF NOT EXISTS duck_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	enabled BOOLEAN NOT NULL,
+	depth REAL NOT NULL,
+	attack_ms INTEGER NOT NULL,
+	release_ms INTEGER NOT NULL,
+	hold_sfx BOOLEAN NOT NULL
+);
+
+/* config::PlaybackPrefs */
+CREATE TABLE IF NOT EXISTS playback_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	volume REAL NOT NULL,
+	bgm REAL NOT NULL,
+	ambience REAL NOT NULL,
+	sfx REAL NOT NULL
+);
+
+/* schedule::ScheduleEntry: kind maps to schedule::ScheduleKind, times are minutes past midnight in tz */
+CREATE TABLE IF NOT EXISTS schedule_entry(
+	guild_id BIGINT NOT NULL,
+	entry_id INTEGER NOT NULL,
+	kind INTEGER NOT NULL,
+	days INTEGER NOT NULL,
+	start_min INTEGER NOT NULL,
+	end_min INTEGER NOT NULL,
+	tz TEXT NOT NULL,
+	PRIMARY KEY (guild_id, entry_id)
+);
+
+/* config::IdleRules */
+CREATE TABLE IF NOT EXISTS idle_config(
+	guild_id BIGINT PRIMARY KEY NOT NULL,
+	timeout_mins INTEGER NOT NULL
+);
+
 COMMIT;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT channel_id, allow FROM channel_filter WHERE guild_id = $1
\ No newline at end of file
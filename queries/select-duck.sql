@@ -0,0 +1 @@
+SELECT enabled, depth, attack_ms, release_ms, hold_sfx FROM duck_config WHERE guild_id = $1
\ No newline at end of file
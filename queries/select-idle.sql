@@ -0,0 +1 @@
+SELECT timeout_mins FROM idle_config WHERE guild_id = $1
\ No newline at end of file
@@ -1 +1 @@
-SELECT mode, channel FROM join_config WHERE guild_id = $1
\ No newline at end of file
+SELECT mode, channel, user_id FROM join_config WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT notice_channel, notice_template FROM opt_in_out WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT volume, bgm, ambience, sfx FROM playback_config WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT ignore_bots, ignore_deafened, ignore_muted, min_users FROM population_config WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT on_join, on_leave, cooldown_secs FROM reaction_config WHERE guild_id = $1
\ No newline at end of file
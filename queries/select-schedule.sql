@@ -0,0 +1 @@
+SELECT entry_id, kind, days, start_min, end_min, tz FROM schedule_entry WHERE guild_id = $1 ORDER BY entry_id
\ No newline at end of file
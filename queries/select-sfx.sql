@@ -0,0 +1 @@
+SELECT name, file_path, duration_ms, size_bytes, uploader FROM guild_sfx WHERE guild_id = $1 ORDER BY name
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT name FROM soundscape_config WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT dwell_secs, margin, debounce_secs FROM switch_config WHERE guild_id = $1
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT trace_key, octet_length(data), created_at FROM trace_store
\ No newline at end of file
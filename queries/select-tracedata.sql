@@ -0,0 +1 @@
+SELECT data FROM trace_store WHERE trace_key = $1
\ No newline at end of file
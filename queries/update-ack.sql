@@ -0,0 +1 @@
+UPDATE user_ack SET used=TRUE WHERE user_id = ANY($1);
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE guild_ack SET used=TRUE WHERE guild_id=$1;
\ No newline at end of file
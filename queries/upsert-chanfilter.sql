@@ -0,0 +1,4 @@
+INSERT INTO channel_filter (guild_id, channel_id, allow)
+VALUES ($1,$2,$3)
+ON CONFLICT (guild_id, channel_id)
+DO UPDATE SET allow=EXCLUDED.allow;
\ No newline at end of file
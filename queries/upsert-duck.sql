@@ -0,0 +1,4 @@
+INSERT INTO duck_config (guild_id, enabled, depth, attack_ms, release_ms, hold_sfx)
+VALUES ($1,$2,$3,$4,$5,$6)
+ON CONFLICT (guild_id)
+DO UPDATE SET enabled=EXCLUDED.enabled, depth=EXCLUDED.depth, attack_ms=EXCLUDED.attack_ms, release_ms=EXCLUDED.release_ms, hold_sfx=EXCLUDED.hold_sfx;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO idle_config (guild_id, timeout_mins)
+VALUES ($1,$2)
+ON CONFLICT (guild_id)
+DO UPDATE SET timeout_mins=EXCLUDED.timeout_mins;
\ No newline at end of file
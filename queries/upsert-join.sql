@@ -1,4 +1,4 @@
-INSERT INTO join_config (guild_id, mode, channel)
-VALUES ($1,$2,$3)
+INSERT INTO join_config (guild_id, mode, channel, user_id)
+VALUES ($1,$2,$3,$4)
 ON CONFLICT (guild_id)
-DO UPDATE SET mode=EXCLUDED.mode, channel=EXCLUDED.channel;
\ No newline at end of file
+DO UPDATE SET mode=EXCLUDED.mode, channel=EXCLUDED.channel, user_id=EXCLUDED.user_id;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO trace_manifest (trace_key, label, length_ns, total_user_count, starting_user_count, optout_user_count, server, region, trace_version)
+VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
+ON CONFLICT (trace_key)
+DO UPDATE SET label=EXCLUDED.label, length_ns=EXCLUDED.length_ns, total_user_count=EXCLUDED.total_user_count, starting_user_count=EXCLUDED.starting_user_count, optout_user_count=EXCLUDED.optout_user_count, server=EXCLUDED.server, region=EXCLUDED.region, trace_version=EXCLUDED.trace_version;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO opt_in_out (guild_id, mode, role_id, notice_channel, notice_template)
+VALUES ($1,$2,$3,$4,$5)
+ON CONFLICT (guild_id)
+DO UPDATE SET notice_channel=EXCLUDED.notice_channel, notice_template=EXCLUDED.notice_template;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO playback_config (guild_id, volume, bgm, ambience, sfx)
+VALUES ($1,$2,$3,$4,$5)
+ON CONFLICT (guild_id)
+DO UPDATE SET volume=EXCLUDED.volume, bgm=EXCLUDED.bgm, ambience=EXCLUDED.ambience, sfx=EXCLUDED.sfx;
\ No newline at end of file
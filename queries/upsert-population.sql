@@ -0,0 +1,4 @@
+INSERT INTO population_config (guild_id, ignore_bots, ignore_deafened, ignore_muted, min_users)
+VALUES ($1,$2,$3,$4,$5)
+ON CONFLICT (guild_id)
+DO UPDATE SET ignore_bots=EXCLUDED.ignore_bots, ignore_deafened=EXCLUDED.ignore_deafened, ignore_muted=EXCLUDED.ignore_muted, min_users=EXCLUDED.min_users;
\ No newline at end of file
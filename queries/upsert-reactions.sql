@@ -0,0 +1,4 @@
+INSERT INTO reaction_config (guild_id, on_join, on_leave, cooldown_secs)
+VALUES ($1,$2,$3,$4)
+ON CONFLICT (guild_id)
+DO UPDATE SET on_join=EXCLUDED.on_join, on_leave=EXCLUDED.on_leave, cooldown_secs=EXCLUDED.cooldown_secs;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO schedule_entry (guild_id, entry_id, kind, days, start_min, end_min, tz)
+VALUES ($1,$2,$3,$4,$5,$6,$7)
+ON CONFLICT (guild_id, entry_id)
+DO UPDATE SET kind=EXCLUDED.kind, days=EXCLUDED.days, start_min=EXCLUDED.start_min, end_min=EXCLUDED.end_min, tz=EXCLUDED.tz;
\ No newline at end of file
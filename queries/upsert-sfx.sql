@@ -0,0 +1,4 @@
+INSERT INTO guild_sfx (guild_id, name, file_path, duration_ms, size_bytes, uploader)
+VALUES ($1,$2,$3,$4,$5,$6)
+ON CONFLICT (guild_id, name)
+DO UPDATE SET file_path=EXCLUDED.file_path, duration_ms=EXCLUDED.duration_ms, size_bytes=EXCLUDED.size_bytes, uploader=EXCLUDED.uploader, created_at=now();
\ No newline at end of file
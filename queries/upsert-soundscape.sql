@@ -0,0 +1,4 @@
+INSERT INTO soundscape_config (guild_id, name)
+VALUES ($1,$2)
+ON CONFLICT (guild_id)
+DO UPDATE SET name=EXCLUDED.name;
\ No newline at end of file
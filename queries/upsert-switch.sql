@@ -0,0 +1,4 @@
+INSERT INTO switch_config (guild_id, dwell_secs, margin, debounce_secs)
+VALUES ($1,$2,$3,$4)
+ON CONFLICT (guild_id)
+DO UPDATE SET dwell_secs=EXCLUDED.dwell_secs, margin=EXCLUDED.margin, debounce_secs=EXCLUDED.debounce_secs;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO trace_store (trace_key, data)
+VALUES ($1,$2)
+ON CONFLICT (trace_key)
+DO UPDATE SET data=EXCLUDED.data;
\ No newline at end of file
@@ -1,52 +1,147 @@
-use crate::constants::*;
+use crate::soundscape::Soundscapes;
 use dashmap::DashMap;
 use serenity::prelude::*;
 use songbird::{
 	self,
-	input::{File, Input},
+	input::{
+		codecs::{CODEC_REGISTRY, PROBE},
+		File,
+		Input,
+	},
+};
+use std::{
+	path::Path,
+	sync::{
+		atomic::{AtomicU32, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+use symphonia::core::{
+	errors::Error as SymphError,
+	formats::FormatOptions,
+	io::{MediaSource, MediaSourceStream},
+	meta::MetadataOptions,
+	probe::Hint,
+	units::TimeBase,
 };
-use std::sync::Arc;
 
 pub struct Resources;
 
-pub type RxMap = Arc<DashMap<&'static str, CachedSound>>;
+pub type RxMap = Arc<DashMap<String, CachedSound>>;
 
 impl TypeMapKey for Resources {
 	type Value = RxMap;
 }
 
-pub struct CachedSound(File<String>);
+pub struct CachedSound {
+	file: File<String>,
+	duration: Option<Duration>,
+	failures: AtomicU32,
+}
+
+impl CachedSound {
+	fn new(path: &str) -> Self {
+		let duration = std::fs::File::open(path).ok().and_then(|f| {
+			let ext = Path::new(path).extension().and_then(|e| e.to_str());
+			measure(Box::new(f), ext)
+		});
+
+		Self {
+			file: File::new(path.to_string()),
+			duration,
+			failures: AtomicU32::new(0),
+		}
+	}
+
+	/// Length of the sound, if Felyne could work it out when loading.
+	pub fn duration(&self) -> Option<Duration> {
+		self.duration
+	}
+
+	/// Notes that the sound failed to play, returning how many times it has.
+	pub fn record_failure(&self) -> u32 {
+		self.failures.fetch_add(1, Ordering::Relaxed) + 1
+	}
+
+	/// Whether the sound has ever failed to play.
+	pub fn is_bad(&self) -> bool {
+		self.failures.load(Ordering::Relaxed) > 0
+	}
+}
 
 impl From<&CachedSound> for Input {
 	fn from(obj: &CachedSound) -> Self {
-		obj.0.clone().into()
+		obj.file.clone().into()
 	}
 }
 
-pub async fn preload_resources() -> RxMap {
-	let resources = DashMap::new();
-	add_resources(&resources, "bgm", BBQ).await;
-	add_resources(&resources, "bgm", BBQ_RESULT).await;
-	add_resources(&resources, "bgm", SLEEP).await;
-	add_resources(&resources, "bgm", START).await;
-	add_resources(&resources, "bgm", AMBIENCE).await;
-	add_resources(&resources, "bgm", BGM).await;
+/// Works out how long a sound lasts, so long as Felyne can decode it.
+pub fn measure(source: Box<dyn MediaSource>, extension: Option<&str>) -> Option<Duration> {
+	let mut hint = Hint::new();
+	if let Some(ext) = extension {
+		hint.with_extension(ext);
+	}
+
+	let mss = MediaSourceStream::new(source, Default::default());
+	let mut format = PROBE
+		.format(
+			&hint,
+			mss,
+			&FormatOptions::default(),
+			&MetadataOptions::default(),
+		)
+		.ok()?
+		.format;
+
+	let track = format.default_track()?;
+
+	CODEC_REGISTRY.get_codec(track.codec_params.codec)?;
+
+	let track_id = track.id;
+	let time_base = track
+		.codec_params
+		.time_base
+		.or_else(|| track.codec_params.sample_rate.map(|r| TimeBase::new(1, r)))?;
+
+	// Not every container knows its length up-front.
+	let frames = match track.codec_params.n_frames {
+		Some(n) => n,
+		None => {
+			let mut n = 0;
+			loop {
+				match format.next_packet() {
+					Ok(packet) if packet.track_id() == track_id => n += packet.dur,
+					Ok(_) => {},
+					Err(SymphError::IoError(_)) => break,
+					Err(_) => return None,
+				}
+			}
+			n
+		},
+	};
 
-	add_resources(&resources, "sfx", SFX).await;
-	add_resources(&resources, "sfx", BONUS_SFX).await;
+	let time = time_base.calc_time(frames);
+
+	Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+}
+
+pub async fn preload_resources(soundscapes: &Soundscapes) -> RxMap {
+	let resources = DashMap::new();
+	add_resources(&resources, soundscapes.tracks()).await;
 
 	Arc::new(resources)
 }
 
 async fn add_resources<'a>(
-	rx: &'a DashMap<&'static str, CachedSound>,
-	folder: &'static str,
-	files: &'static [&'static str],
+	rx: &DashMap<String, CachedSound>,
+	files: impl Iterator<Item = &'a str>,
 ) {
-	for file_id in files {
-		rx.insert(
-			file_id,
-			CachedSound(File::new(format!("{}/{}", folder, file_id))),
-		);
+	for file in files {
+		add_resource(rx, file);
 	}
 }
+
+pub fn add_resource(rx: &DashMap<String, CachedSound>, file: &str) {
+	rx.insert(file.to_string(), CachedSound::new(file));
+}
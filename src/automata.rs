@@ -77,7 +77,7 @@ impl Cooldown {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TimedMachine<State: Hash + Eq + Copy, Alphabet: Hash + Eq> {
 	start: State,
 	state: State,
@@ -115,6 +115,20 @@ impl<State: Hash + Eq + Copy, Alphabet: Hash + Eq + Copy> TimedMachine<State, Al
 		}
 	}
 
+	/// When a transition on `token` from the current state comes off
+	/// cooldown, if there are any. This may already have passed.
+	pub fn ready_at(&self, token: Alphabet) -> Option<Instant> {
+		self.transitions
+			.get(&self.state)
+			.and_then(|token_map| token_map.get(&token))
+			.and_then(|tx_list| {
+				tx_list
+					.iter()
+					.map(|tx| tx.last_used + tx.cooldown.unwrap_or_default())
+					.min()
+			})
+	}
+
 	pub fn cause_cooldown(
 		&mut self,
 		from: State,
@@ -331,6 +345,23 @@ mod test {
 		assert_eq!(machine.advance(TestAlpha::A), Some(TestState::C));
 	}
 
+	#[test]
+	fn test_ready_at_follows_cooldowns() {
+		let mut machine = TimedMachine::new(TestState::A);
+		let cd = Cooldown::new(Duration::from_secs(200).into(), false, true);
+
+		machine
+			.add_priority_transition(TestState::A, TestState::B, TestAlpha::A, 0, Some(cd))
+			.add_transition(TestState::B, TestState::A, TestAlpha::B);
+
+		let wait = machine.ready_at(TestAlpha::A).unwrap() - Instant::now();
+		assert!(wait > Duration::from_secs(199));
+		assert_eq!(machine.ready_at(TestAlpha::B), None);
+
+		machine.add_transition(TestState::A, TestState::C, TestAlpha::A);
+		assert!(machine.ready_at(TestAlpha::A).unwrap() <= Instant::now());
+	}
+
 	#[test]
 	fn test_refresh_goes_to_original_state() {
 		let mut machine = TimedMachine::new(TestState::A);
@@ -3,22 +3,34 @@ use super::*;
 use crate::{
 	config::{
 		ConfigParseError,
+		ConsentNotice,
 		Control as CfgControl,
 		ControlMode,
+		DuckRules,
 		GatherMode,
+		IdleRules,
 		OptInOut,
 		OptInOutMode,
+		PopulationRules,
+		ReactionRules,
+		SwitchRules,
 	},
+	constants::{IDLE_MAX_MINUTES, REACTION_MAX_COOLDOWN_SECS, SWITCH_MAX_MARGIN, SWITCH_MAX_SECS},
 	guild::*,
+	schedule::{Days, ScheduleEntry, ScheduleKind},
 	server::Label,
+	soundscape::SoundscapeKey,
+	voicehunt::{voicehunt_control, voicehunt_rescan, VoiceHuntCommand},
 	watchcat::*,
 };
 
+use chrono_tz::Tz;
 use serenity::{
 	builder::CreateMessage,
 	client::*,
 	framework::standard::{macros::command, Args, CommandResult},
 	model::prelude::*,
+	utils::MessageBuilder,
 };
 use std::sync::Arc;
 
@@ -130,6 +142,72 @@ pub async fn felyne_prefix(ctx: &Context, msg: &Message, mut args: Args) -> Comm
 	Ok(())
 }
 
+#[command]
+#[description = "Mrrp? (What should the hunt sound like around here?)"]
+#[owner_privilege]
+pub async fn soundscape(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let (gs, soundscapes) = {
+		let data = ctx.data.read().await;
+		(
+			Arc::clone(data.get::<GuildStates>().unwrap()),
+			Arc::clone(data.get::<SoundscapeKey>().unwrap()),
+		)
+	};
+
+	let name = match args.single::<String>() {
+		Ok(name) => name,
+		Err(_) => {
+			check_msg(
+				msg.channel_id
+					.say(
+						&ctx.http,
+						format!("I know the soundscapes: {:?}", soundscapes.names()),
+					)
+					.await,
+			);
+			return Ok(());
+		},
+	};
+
+	if !soundscapes.contains(&name) {
+		check_msg(
+			msg.channel_id
+				.say(
+					&ctx.http,
+					format!(
+						"Mrowr?! I don't know that one! I know: {:?}",
+						soundscapes.names()
+					),
+				)
+				.await,
+		);
+		return Ok(());
+	}
+
+	if let Some(state) = gs.get(&guild_id) {
+		let mut lock = state.write().await;
+		lock.set_soundscape(name.clone()).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(
+				&ctx.http,
+				format!("Nya! (I'll bring the {} sounds next time I join!)", name),
+			)
+			.await,
+	);
+
+	Ok(())
+}
+
 #[command]
 #[aliases("admin-ctl-mode")]
 #[description = "Mrrewr? (Who gets to boss me around, all the time?)"]
@@ -271,6 +349,76 @@ pub async fn server_opt(ctx: &Context, msg: &Message, mut args: Args) -> Command
 	Ok(())
 }
 
+#[command]
+#[aliases("consent-notice")]
+#[description = "Mrr? (Should I tell everyone when I start or stop listening in?)"]
+#[owner_privilege]
+pub async fn consent_notice(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	if args.is_empty() {
+		check_msg(
+			msg.channel_id
+				.say(
+					&ctx.http,
+					format!(
+						"Give me a channel (and optionally a message), or `off`. \
+						The default message is: {:?}",
+						ConsentNotice::DEFAULT_TEMPLATE,
+					),
+				)
+				.await,
+		);
+		return Ok(());
+	}
+
+	let notice = if args.current().map(|s| s.eq_ignore_ascii_case("off")) == Some(true) {
+		None
+	} else {
+		let channel = match parse_chan_mention(&mut args) {
+			Some(c) => c,
+			None => {
+				return confused(ctx, msg).await;
+			},
+		};
+
+		let template = args.rest().trim();
+
+		Some(ConsentNotice {
+			channel,
+			template: if template.is_empty() {
+				None
+			} else {
+				Some(template.to_string())
+			},
+		})
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	if let Some(state) = gs.get(&g_id) {
+		let mut lock = state.write().await;
+		lock.set_consent_notice(notice.clone()).await;
+	}
+
+	let reply = match notice {
+		Some(n) => format!("Posting consent notices in {}.", n.channel.mention()),
+		None => "No longer posting consent notices.".to_string(),
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
 #[command]
 #[aliases("server-ack")]
 #[description = "Mraww? (If I'm measuring how folks talk, should I credit this place?)"]
@@ -466,3 +614,568 @@ pub async fn gather_mode(ctx: &Context, msg: &Message, mut args: Args) -> Comman
 
 	Ok(())
 }
+
+#[command]
+#[aliases("allow-channel")]
+#[description = "Nya! (Only hunt in channels you've allowed, if you allow any.)"]
+#[owner_privilege]
+pub async fn allow_channel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	filter_channel(ctx, msg, args, Some(true)).await
+}
+
+#[command]
+#[aliases("deny-channel")]
+#[description = "Hiss! (Never wander into this channel by myself.)"]
+#[owner_privilege]
+pub async fn deny_channel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	filter_channel(ctx, msg, args, Some(false)).await
+}
+
+#[command]
+#[aliases("reset-channel")]
+#[description = "Mrr? (Forget whether I'm allowed in this channel.)"]
+#[owner_privilege]
+pub async fn reset_channel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	filter_channel(ctx, msg, args, None).await
+}
+
+async fn filter_channel(
+	ctx: &Context,
+	msg: &Message,
+	mut args: Args,
+	allow: Option<bool>,
+) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let channel = match parse_chan_mention(&mut args) {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	if let Some(state) = gs.get(&g_id) {
+		let mut lock = state.write().await;
+		lock.set_channel_filter(channel, allow).await;
+	}
+
+	voicehunt_rescan(ctx, g_id).await;
+
+	let reply = match allow {
+		Some(true) => format!("Hunting in {} is allowed.", channel.mention()),
+		Some(false) => format!("I won't hunt in {} by myself.", channel.mention()),
+		None => format!("Forgot my rules for {}.", channel.mention()),
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+#[command]
+#[description = "Mrr? (Who should I count when picking the busiest channel?)"]
+#[owner_privilege]
+pub async fn population(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&g_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut rules = state.read().await.population_rules();
+
+	if !args.is_empty() {
+		let key = args.single::<String>().unwrap_or_default().to_lowercase();
+		let value = args.single::<String>().unwrap_or_default().to_lowercase();
+		let flag = parse_flag(&value);
+
+		match (key.as_str(), flag) {
+			("bots", Some(f)) => rules.ignore_bots = f,
+			("deafened", Some(f)) => rules.ignore_deafened = f,
+			("muted", Some(f)) => rules.ignore_muted = f,
+			("min", _) => match value.parse::<u64>() {
+				Ok(n) if n >= 1 => rules.min_users = n,
+				_ => {
+					return confused(ctx, msg).await;
+				},
+			},
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		}
+
+		state.write().await.set_population_rules(rules).await;
+
+		voicehunt_rescan(ctx, g_id).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(&ctx.http, describe_population(&rules))
+			.await,
+	);
+
+	Ok(())
+}
+
+fn describe_population(rules: &PopulationRules) -> String {
+	let yn = |b: bool| if b { "ignored" } else { "counted" };
+
+	format!(
+		"Bots are {}, deafened users are {}, and self-muted users are {}. \
+		I'll only hunt by myself once a channel has {} user(s).",
+		yn(rules.ignore_bots),
+		yn(rules.ignore_deafened),
+		yn(rules.ignore_muted),
+		rules.min_users,
+	)
+}
+
+#[command]
+#[description = "Mya? (How long should I wait before chasing a busier channel?)"]
+#[owner_privilege]
+pub async fn switching(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&g_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut rules = state.read().await.switch_rules();
+
+	if !args.is_empty() {
+		let key = args.single::<String>().unwrap_or_default().to_lowercase();
+		let value = match args.single::<u64>() {
+			Ok(v) => v,
+			Err(_) => {
+				return confused(ctx, msg).await;
+			},
+		};
+
+		match key.as_str() {
+			"dwell" if value <= SWITCH_MAX_SECS => rules.dwell_secs = value,
+			"margin" if value <= SWITCH_MAX_MARGIN => rules.margin = value,
+			"debounce" if value <= SWITCH_MAX_SECS => rules.debounce_secs = value,
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		}
+
+		state.write().await.set_switch_rules(rules).await;
+
+		voicehunt_rescan(ctx, g_id).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(&ctx.http, describe_switching(&rules))
+			.await,
+	);
+
+	Ok(())
+}
+
+fn describe_switching(rules: &SwitchRules) -> String {
+	format!(
+		"I'll stay in a channel for at least {}s, and only move to one with {} more user(s) \
+		once it's been busier for {}s.",
+		rules.dwell_secs, rules.margin, rules.debounce_secs,
+	)
+}
+
+#[command]
+#[description = "Mrowr! (Should I greet folks who join my channel, and mourn those who leave?)"]
+#[owner_privilege]
+pub async fn reactions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&g_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut rules = state.read().await.reaction_rules();
+
+	if !args.is_empty() {
+		let key = args.single::<String>().unwrap_or_default().to_lowercase();
+		let value = args.single::<String>().unwrap_or_default().to_lowercase();
+		let flag = parse_flag(&value);
+
+		match (key.as_str(), flag) {
+			("join", Some(f)) => rules.on_join = f,
+			("leave", Some(f)) => rules.on_leave = f,
+			("cooldown", _) => match value.parse::<u64>() {
+				Ok(n) if n <= REACTION_MAX_COOLDOWN_SECS => rules.cooldown_secs = n,
+				_ => {
+					return confused(ctx, msg).await;
+				},
+			},
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		}
+
+		state.write().await.set_reaction_rules(rules).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(&ctx.http, describe_reactions(&rules))
+			.await,
+	);
+
+	Ok(())
+}
+
+fn describe_reactions(rules: &ReactionRules) -> String {
+	let onoff = |b: bool| if b { "on" } else { "off" };
+
+	format!(
+		"Join sounds are {}, leave sounds are {}, and I'll wait {}s between them.",
+		onoff(rules.on_join),
+		onoff(rules.on_leave),
+		rules.cooldown_secs,
+	)
+}
+
+#[command]
+#[description = "Mrr... (Should I play quieter while folks are talking?)"]
+#[owner_privilege]
+pub async fn duck(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&g_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut rules = state.read().await.duck_rules();
+
+	if !args.is_empty() {
+		let key = args.single::<String>().unwrap_or_default().to_lowercase();
+		let value = args.single::<String>().unwrap_or_default().to_lowercase();
+		let flag = parse_flag(&value);
+
+		match (key.as_str(), flag) {
+			("on", _) => rules.enabled = true,
+			("off", _) => rules.enabled = false,
+			("hold-sfx", Some(f)) => rules.hold_sfx = f,
+			("depth", _) => match value.parse::<f32>() {
+				Ok(d) if (0.0..=1.0).contains(&d) => rules.depth = d,
+				_ => {
+					return confused(ctx, msg).await;
+				},
+			},
+			("attack", _) | ("release", _) => match value.parse::<u64>() {
+				Ok(ms) if key == "attack" => rules.attack_ms = ms,
+				Ok(ms) => rules.release_ms = ms,
+				_ => {
+					return confused(ctx, msg).await;
+				},
+			},
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		}
+
+		state.write().await.set_duck_rules(rules).await;
+
+		voicehunt_control(ctx, g_id, VoiceHuntCommand::Duck(rules)).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(&ctx.http, describe_ducking(&rules))
+			.await,
+	);
+
+	Ok(())
+}
+
+fn describe_ducking(rules: &DuckRules) -> String {
+	if rules.enabled {
+		format!(
+			"While folks talk, I'll play at {:.0}% volume, getting quieter over {}ms \
+			and recovering over {}ms.{}",
+			rules.depth * 100.0,
+			rules.attack_ms,
+			rules.release_ms,
+			if rules.hold_sfx {
+				" I'll also save my mewls for a quiet moment."
+			} else {
+				""
+			},
+		)
+	} else {
+		"I won't get any quieter when folks talk.".to_string()
+	}
+}
+
+#[command]
+#[description = "Mrr... (How long should I wait in a quiet channel before napping?)"]
+#[owner_privilege]
+pub async fn idle(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&g_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut rules = state.read().await.idle_rules();
+
+	if !args.is_empty() {
+		let value = args.single::<String>().unwrap_or_default().to_lowercase();
+
+		rules.timeout_mins = match value.as_str() {
+			"off" | "never" => 0,
+			mins => match mins.parse::<u64>() {
+				Ok(m) if m <= IDLE_MAX_MINUTES => m,
+				_ => {
+					return confused(ctx, msg).await;
+				},
+			},
+		};
+
+		state.write().await.set_idle_rules(rules).await;
+
+		voicehunt_rescan(ctx, g_id).await;
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, describe_idle(&rules)).await);
+
+	Ok(())
+}
+
+fn describe_idle(rules: &IdleRules) -> String {
+	const WAKE: &str = "Once I'm napping, only someone who isn't deafened joining, leaving or \
+		changing their voice state wakes me, or you sending me somewhere.";
+
+	match rules.timeout_mins {
+		0 => "I'll stay put, however quiet it gets.".to_string(),
+		1 => format!(
+			"If nobody's talked (or can hear me) for a minute, I'll nap. {}",
+			WAKE
+		),
+		mins => format!(
+			"If nobody's talked (or can hear me) for {} minutes, I'll nap. {}",
+			mins, WAKE
+		),
+	}
+}
+
+#[command]
+#[aliases("schedule-list")]
+#[description = "Mrr? (When have you asked me to hunt, watch, or keep quiet?)"]
+#[owner_privilege]
+pub async fn schedule_list(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let mut builder = MessageBuilder::new();
+
+	if let Some(state) = gs.get(&g_id) {
+		let lock = state.read().await;
+
+		if lock.schedule().is_empty() {
+			builder.push_line("Nya... (Nyothing scheduled here yet!)");
+		} else {
+			builder.push_bold_line("My schedule here:");
+			for entry in lock.schedule() {
+				builder.push_line(entry.to_string());
+			}
+		}
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, builder.build()).await);
+
+	Ok(())
+}
+
+#[command]
+#[aliases("schedule-add")]
+#[description = "Nya! (Tell me when to hunt, watch, or keep quiet, e.g. \
+	`hunt fri 20:00-23:00 Europe/London` or `quiet daily 23:00-07:00`. Days can be `daily`, \
+	`weekdays`, `weekends`, or a list like `mon,wed`. Times are in UTC unless you name a time zone.)"]
+#[owner_privilege]
+pub async fn schedule_add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let kind = args
+		.single::<String>()
+		.ok()
+		.and_then(|k| ScheduleKind::from_str(&k.to_lowercase()));
+	let days = args
+		.single::<String>()
+		.ok()
+		.and_then(|d| Days::parse(&d.to_lowercase()));
+	let window = args
+		.single::<String>()
+		.ok()
+		.and_then(|w| ScheduleEntry::parse_window(&w));
+	let tz = match args.single::<String>() {
+		Ok(name) => name.parse::<Tz>().ok(),
+		Err(_) => Some(Tz::UTC),
+	};
+
+	let entry = match (kind, days, window, tz) {
+		(Some(kind), Some(days), Some((start, end)), Some(tz)) => ScheduleEntry {
+			id: 0,
+			kind,
+			days,
+			start,
+			end,
+			tz,
+		},
+		_ => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	if let Some(state) = gs.get(&g_id) {
+		let id = state.write().await.add_schedule_entry(entry).await;
+
+		check_msg(
+			msg.channel_id
+				.say(&ctx.http, format!("Mrowr! (Added entry #{}.)", id))
+				.await,
+		);
+	}
+
+	Ok(())
+}
+
+#[command]
+#[aliases("schedule-remove")]
+#[description = "Mya!? (Forget one of my schedule entries, by its number.)"]
+#[owner_privilege]
+pub async fn schedule_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let g_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let id = match args.single::<String>() {
+		Ok(id) => match id.trim_start_matches('#').parse::<u32>() {
+			Ok(id) => id,
+			Err(_) => {
+				return confused(ctx, msg).await;
+			},
+		},
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let removed = match gs.get(&g_id) {
+		Some(state) => state.write().await.remove_schedule_entry(id).await,
+		None => None,
+	};
+
+	let reply = match removed {
+		Some(entry) => format!("Mrr... (Forgot {}.)", entry),
+		None => "Myeh? (I don't have an entry with that number.)".to_string(),
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
@@ -1,16 +1,25 @@
 use super::*;
 
 use crate::{
+	constants::{BGM_HISTORY_LENGTH, QUEST_MAX_MINUTES, QUEST_WARNINGS},
 	guild::*,
-	voicehunt::{mode::Join, *},
+	soundscape::SoundClass,
+	voicehunt::{
+		mode::Join,
+		quest::{self as q, QuestCommand, QuestStatus},
+		status::HuntStatus,
+		*,
+	},
 };
 
 use serenity::{
 	client::*,
 	framework::standard::{macros::command, Args, CommandResult},
 	model::prelude::*,
+	utils::MessageBuilder,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::RwLock;
 
 #[command]
 #[description = "Mraa! (I'll come hang out wherever folks are, or what channel you tell me!)"]
@@ -92,6 +101,46 @@ pub async fn watch(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
 	Ok(())
 }
 
+#[command]
+#[description = "Nya! (I'll tag along with you, or whoever you mention, wherever they go!)"]
+#[owner_privilege]
+pub async fn follow(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let user = if args.is_empty() {
+		msg.author.id
+	} else {
+		match parse_user_mention(&mut args) {
+			Some(u) if u != ctx.cache.current_user().id => u,
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		}
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	if let Some(state) = gs.get(&guild) {
+		let mut lock = state.write().await;
+		lock.set_join(Join::Follow(user)).await;
+	}
+
+	voicehunt_control(ctx, guild, VoiceHuntCommand::Follow(user)).await;
+
+	check_msg(msg.channel_id.say(&ctx.http, "Mrowr!").await);
+
+	Ok(())
+}
+
 #[command]
 #[description = "Myowr! (Another hunt ends in success!)"]
 #[owner_privilege]
@@ -145,7 +194,442 @@ pub async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
 		return confused(&ctx, &msg).await;
 	}
 
+	if let Some(state) = guild_state(ctx, guild).await {
+		let mut lock = state.write().await;
+		let mut prefs = lock.playback();
+		prefs.volume = vol;
+		lock.set_playback(prefs).await;
+	}
+
 	voicehunt_control(&ctx, guild, VoiceHuntCommand::Volume(vol)).await;
 
 	Ok(())
 }
+
+#[command]
+#[description = "Mrr? (Make my music, ambience, or mewls louder or quieter than the rest.)"]
+#[owner_privilege]
+pub async fn mix(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let state = match guild_state(ctx, guild).await {
+		Some(s) => s,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let mut prefs = state.read().await.playback();
+
+	if !args.is_empty() {
+		let class = match args
+			.single::<String>()
+			.unwrap_or_default()
+			.to_lowercase()
+			.as_str()
+		{
+			"bgm" | "music" => SoundClass::Bgm,
+			"ambience" => SoundClass::Ambience,
+			"sfx" => SoundClass::Sfx,
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		};
+
+		let mult = match args.single::<f32>() {
+			Ok(m) if m.is_finite() && (0.0..=2.0).contains(&m) => m,
+			_ => {
+				return confused(ctx, msg).await;
+			},
+		};
+
+		*prefs.multiplier_mut(class) = mult;
+		state.write().await.set_playback(prefs).await;
+
+		voicehunt_control(ctx, guild, VoiceHuntCommand::Mix(prefs)).await;
+	}
+
+	check_msg(
+		msg.channel_id
+			.say(
+				&ctx.http,
+				format!(
+					"Volume {:.2}: music x{:.2}, ambience x{:.2}, sfx x{:.2}.",
+					prefs.volume, prefs.bgm, prefs.ambience, prefs.sfx
+				),
+			)
+			.await,
+	);
+
+	Ok(())
+}
+
+async fn guild_state(ctx: &Context, guild: GuildId) -> Option<Arc<RwLock<GuildState>>> {
+	let data = ctx.data.read().await;
+	data.get::<GuildStates>()
+		.and_then(|gs| gs.get(&guild).map(|s| Arc::clone(s.value())))
+}
+
+#[command]
+#[description = "Nya! (I'll make a noise, right now! Any of mine, or one this server taught me.)"]
+#[owner_privilege]
+pub async fn sfx(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let name = match args.single::<String>() {
+		Ok(n) => n.to_lowercase(),
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&guild) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let soundscape = guild_soundscape(ctx, &state).await;
+
+	let custom = {
+		let lock = state.read().await;
+		lock.custom_sfx()
+			.iter()
+			.find(|s| s.name == name)
+			.map(|s| s.file.clone())
+	};
+
+	let found = match custom {
+		Some(file) => Some((file, soundscape.sfx.custom_volume().unwrap_or_default())),
+		None => soundscape
+			.sfx
+			.find_track(&name)
+			.map(|(file, vol)| (file.to_string(), vol)),
+	};
+
+	match found {
+		Some((file, vol)) => voicehunt_control(ctx, guild, VoiceHuntCommand::Sfx(file, vol)).await,
+		None => check_msg(
+			msg.channel_id
+				.say(&ctx.http, "Myeh? (I don't knyow that one.)")
+				.await,
+		),
+	}
+
+	Ok(())
+}
+
+#[command]
+#[description = "Mrrp! (Name a track, and I'll play it nyext!)"]
+#[owner_privilege]
+pub async fn play(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let name = match args.single::<String>() {
+		Ok(n) => n,
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let state = match gs.get(&guild) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let soundscape = guild_soundscape(ctx, &state).await;
+
+	let reply = match soundscape.bgm.find_track(&name) {
+		Some((file, vol)) => {
+			voicehunt_control(
+				ctx,
+				guild,
+				VoiceHuntCommand::QueueBgm(file.to_string(), vol),
+			)
+			.await;
+			"Mrowr! (That one's nyext!)"
+		},
+		None => "Myeh? (I don't knyow that one.)",
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+#[command]
+#[description = "Myeh... (On to the nyext track!)"]
+#[owner_privilege]
+pub async fn skip(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	voicehunt_control(ctx, guild, VoiceHuntCommand::Skip).await;
+
+	Ok(())
+}
+
+#[command]
+#[aliases("now-playing", "np")]
+#[description = "Mrr? (What am I playing right nyow?)"]
+pub async fn now_playing(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let reply = match voicehunt_status(ctx, guild).await {
+		Some(HuntStatus {
+			stealthy: true,
+			state,
+			..
+		}) => format!("... (Keeping quiet. I'd be in *{}* right nyow.)", state),
+		Some(HuntStatus {
+			now_playing: Some((played, elapsed)),
+			state,
+			queued,
+			channel,
+			track_errors,
+			..
+		}) => {
+			let mut out = format!(
+				"Nya! Playing `{}` from *{}*, {} in.",
+				played.track,
+				state,
+				clock(elapsed)
+			);
+
+			if let Some(channel) = channel {
+				out.push_str(&format!(" Come listen in {}!", channel.mention()));
+			}
+
+			if queued > 0 {
+				out.push_str(&format!(" ({} more queued up!)", queued));
+			}
+
+			if track_errors > 0 {
+				out.push_str(&format!(
+					" (Mrr... {} track(s) wouldn't play, so I skipped them.)",
+					track_errors
+				));
+			}
+
+			out
+		},
+		Some(HuntStatus { state, .. }) => format!("Mrr... (Nyothing playing. I'm in *{}*.)", state),
+		None => "Mrr... (I'm nyot out hunting right now.)".to_string(),
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+#[command]
+#[description = "Mrrp! (What have I played lately? Ask for up to 20.)"]
+pub async fn history(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let count = args
+		.single::<usize>()
+		.unwrap_or(5)
+		.clamp(1, BGM_HISTORY_LENGTH);
+
+	let history = voicehunt_status(ctx, guild)
+		.await
+		.map(|s| s.history)
+		.unwrap_or_default();
+
+	let mut builder = MessageBuilder::new();
+
+	if history.is_empty() {
+		builder.push_line("Mrr... (I haven't played anything lately!)");
+	} else {
+		builder.push_bold_line("Lately, I've played:");
+		for played in history.iter().take(count) {
+			builder.push_mono(&played.track);
+			builder.push_line(format!(
+				" -- {}, {} ago",
+				played.state.as_deref().unwrap_or("by request"),
+				clock(played.started.elapsed())
+			));
+		}
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, builder.build()).await);
+
+	Ok(())
+}
+
+#[command]
+#[description = "Mrowr! (I'll time a quest for you! Tell me how many minutes, and when to warn you, \
+	e.g. `!quest 50 10 5`. Then you can `pause`, `resume`, `extend <minutes>`, `clear` or `abandon` it.)"]
+#[owner_privilege]
+pub async fn quest(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	// Get the guild ID.
+	let guild = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let status = match voicehunt_status(ctx, guild).await {
+		Some(s) => s.quest,
+		None => {
+			check_msg(
+				msg.channel_id
+					.say(&ctx.http, "Mrr... (I'm nyot out hunting right now.)")
+					.await,
+			);
+			return Ok(());
+		},
+	};
+
+	let first = args.single::<String>().ok().map(|s| s.to_lowercase());
+
+	let (cmd, reply) = match (first.as_deref(), status) {
+		(None, Some(QuestStatus { remaining, paused })) => (
+			None,
+			format!(
+				"Mrowr! ({} left on the quest{}.)",
+				q::time_left(remaining),
+				if paused { ", paused" } else { "" }
+			),
+		),
+		(None, None) => (
+			None,
+			"Mrr? (Nyo quest right nyow. Start one with `!quest <minutes>`!)".to_string(),
+		),
+		(Some("pause"), Some(_)) => (Some(QuestCommand::Pause), "Mrr... (Quest paused.)".into()),
+		(Some("resume"), Some(_)) => (Some(QuestCommand::Resume), "Mrowr! (Back to it!)".into()),
+		(Some("extend"), Some(_)) => match args.single::<u64>().ok().and_then(quest_minutes) {
+			Some(by) => (
+				Some(QuestCommand::Extend(by)),
+				format!("Nya! ({} more on the clock!)", q::time_left(by)),
+			),
+			None => {
+				return confused(ctx, msg).await;
+			},
+		},
+		(Some("clear"), Some(_)) => (
+			Some(QuestCommand::Clear),
+			"Nyaa! (Quest complete! Good hunting!)".into(),
+		),
+		(Some("abandon"), Some(_)) => (
+			Some(QuestCommand::Abandon),
+			"Mrr... (Quest abandoned. Back to camp...)".into(),
+		),
+		(Some("pause" | "resume" | "extend" | "clear" | "abandon"), None) => (
+			None,
+			"Mrr? (Nyo quest right nyow. Start one with `!quest <minutes>`!)".to_string(),
+		),
+		(Some(_), Some(_)) => (
+			None,
+			"Myeh! (Clear or abandon this quest before starting another!)".to_string(),
+		),
+		(Some(length), None) => {
+			let length = match length.parse().ok().and_then(quest_minutes) {
+				Some(l) => l,
+				None => {
+					return confused(ctx, msg).await;
+				},
+			};
+
+			let warnings = if args.is_empty() {
+				QUEST_WARNINGS
+					.iter()
+					.map(|m| Duration::from_secs(m * 60))
+					.collect()
+			} else {
+				match args
+					.iter::<u64>()
+					.map(|m| m.ok().and_then(quest_minutes))
+					.collect::<Option<Vec<_>>>()
+				{
+					Some(w) => w,
+					None => {
+						return confused(ctx, msg).await;
+					},
+				}
+			};
+
+			(
+				Some(QuestCommand::Start {
+					length,
+					warnings,
+					channel: msg.channel_id,
+				}),
+				format!(
+					"Mrowr! (Quest accepted: {} on the clock!)",
+					q::time_left(length)
+				),
+			)
+		},
+	};
+
+	if let Some(cmd) = cmd {
+		voicehunt_control(ctx, guild, VoiceHuntCommand::Quest(cmd)).await;
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+fn quest_minutes(mins: u64) -> Option<Duration> {
+	(1..=QUEST_MAX_MINUTES)
+		.contains(&mins)
+		.then(|| Duration::from_secs(mins * 60))
+}
+
+fn clock(time: Duration) -> String {
+	let secs = time.as_secs();
+
+	format!("{}:{:02}", secs / 60, secs % 60)
+}
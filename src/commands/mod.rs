@@ -3,9 +3,11 @@ mod cat_control;
 mod checks;
 mod info;
 mod opt;
+mod owner;
+mod sfx;
 mod utils;
 
-use self::{admin::*, cat_control::*, checks::*, info::*, opt::*, utils::*};
+use self::{admin::*, cat_control::*, checks::*, info::*, opt::*, owner::*, sfx::*, utils::*};
 
 use serenity::{
 	client::Context,
@@ -24,7 +26,7 @@ use std::collections::HashSet;
 #[group]
 #[description = "Info about me!"]
 #[summary = "Info!"]
-#[commands(github, info, optin, optout, ack, remove_ack)]
+#[commands(github, info, optin, optout, ack, remove_ack, now_playing, history)]
 struct Everyone;
 
 #[group]
@@ -33,7 +35,7 @@ struct Everyone;
 #[description = "Tell me where to hunt, and I'll go there!"]
 #[summary = "Hunting!"]
 #[only_in(guilds)]
-#[commands(hunt, cart, volume, watch)]
+#[commands(hunt, cart, volume, mix, watch, follow, sfx, play, skip, quest)]
 struct Control;
 
 #[group]
@@ -46,17 +48,41 @@ struct Control;
 	see_config,
 	log_to,
 	felyne_prefix,
+	soundscape,
 	admin_ctl_mode,
 	ctl_mode,
 	server_opt,
+	consent_notice,
 	server_ack,
 	remove_server_ack,
 	server_label,
 	server_unlabel,
-	gather_mode
+	gather_mode,
+	sfx_upload,
+	sfx_list,
+	sfx_remove,
+	sfx_preview,
+	allow_channel,
+	deny_channel,
+	reset_channel,
+	population,
+	switching,
+	reactions,
+	duck,
+	idle,
+	schedule_list,
+	schedule_add,
+	schedule_remove
 )]
 struct Admin;
 
+#[group]
+#[owners_only]
+#[description = "Bot owner housekeeping."]
+#[summary = "Owner!"]
+#[commands(trace_usage)]
+struct Owner;
+
 #[help]
 #[individual_command_tip = "Mrowr! (Hello! I'm here to bring the sounds \
 	of the Hunt to your voice calls!)\n\n\
@@ -0,0 +1,79 @@
+use super::*;
+
+use crate::trace_sink::TraceSinkKey;
+
+use serenity::{
+	client::*,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::prelude::*,
+	utils::MessageBuilder,
+};
+use std::{sync::Arc, time::SystemTime};
+
+#[command]
+#[aliases("trace-usage")]
+#[description = "Mrrr... (How full is my bag of measurements?)"]
+pub async fn trace_usage(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+	let store = {
+		let data = ctx.data.read().await;
+		data.get::<TraceSinkKey>().map(Arc::clone)
+	};
+
+	let store = match store {
+		Some(s) => s,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let usage = store.usage();
+	let rules = store.retention();
+
+	let mut builder = MessageBuilder::new();
+
+	builder.push_bold_line("Trace storage:");
+
+	match usage.last_scan {
+		Some(scan) => {
+			builder.push_line(format!(
+				"{} traces, {} bytes (as of {}s ago).",
+				usage.files,
+				usage.bytes,
+				SystemTime::now()
+					.duration_since(scan)
+					.unwrap_or_default()
+					.as_secs()
+			));
+
+			if let Some(oldest) = usage.oldest {
+				builder.push_line(format!(
+					"Oldest trace is {}s old.",
+					SystemTime::now()
+						.duration_since(oldest)
+						.unwrap_or_default()
+						.as_secs()
+				));
+			}
+		},
+		None => {
+			builder.push_line(format!(
+				"{} traces, {} bytes written since startup (not yet scanned).",
+				usage.files, usage.bytes
+			));
+		},
+	}
+
+	builder.push("Limits: ");
+	builder.push_italic_line(format!(
+		"max age {:?}s, max bytes {:?}, max files {:?}",
+		rules.max_age_secs, rules.max_total_bytes, rules.max_files
+	));
+
+	if usage.over_quota {
+		builder.push_bold_line("Over quota: new captures are being refused!");
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, builder.build()).await);
+
+	Ok(())
+}
@@ -0,0 +1,317 @@
+use super::*;
+
+use crate::{audio_resources::*, config::CustomSfxConfig, custom_sfx::*, guild::*};
+
+use serenity::{
+	builder::{CreateAttachment, CreateMessage},
+	client::*,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::prelude::*,
+	utils::MessageBuilder,
+};
+use std::{path::Path, sync::Arc};
+use tokio::{fs, sync::RwLock};
+use tracing::*;
+
+#[command]
+#[aliases("sfx-upload")]
+#[description = "Mrowr! (Teach me a nyew sound! Attach it to your message.)"]
+#[owner_privilege]
+pub async fn sfx_upload(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let name = match args.single::<String>() {
+		Ok(n) => n.to_lowercase(),
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let attachment = match msg.attachments.first() {
+		Some(a) => a,
+		None => {
+			check_msg(
+				msg.channel_id
+					.say(&ctx.http, "Mya? (Attach a sound to your message!)")
+					.await,
+			);
+			return Ok(());
+		},
+	};
+
+	let (gs, resources, cfg) = {
+		let data = ctx.data.read().await;
+		(
+			Arc::clone(data.get::<GuildStates>().unwrap()),
+			Arc::clone(data.get::<Resources>().unwrap()),
+			Arc::clone(data.get::<CustomSfxSettings>().unwrap()),
+		)
+	};
+
+	let state = match gs.get(&guild_id) {
+		Some(s) => Arc::clone(s.value()),
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let reply = match upload(&state, &resources, &cfg, name, attachment, msg.author.id).await {
+		Ok(sfx) => format!(
+			"Nya! (I learned {:?}: {:.1}s long!)",
+			sfx.name,
+			sfx.duration.as_secs_f64()
+		),
+		Err(e) => {
+			if let UploadError::Io(e) = &e {
+				error!("Couldn't store custom SFX for {:?}: {:?}", guild_id, e);
+			}
+			e.explain(&cfg)
+		},
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+async fn upload(
+	state: &Arc<RwLock<GuildState>>,
+	resources: &RxMap,
+	cfg: &CustomSfxConfig,
+	name: String,
+	attachment: &Attachment,
+	uploader: UserId,
+) -> Result<CustomSfx, UploadError> {
+	if !valid_name(&name) {
+		return Err(UploadError::BadName);
+	}
+
+	let (guild_id, others) = {
+		let lock = state.read().await;
+		let others = lock
+			.custom_sfx()
+			.iter()
+			.filter(|s| s.name != name)
+			.count();
+		(lock.guild(), others)
+	};
+
+	if others >= cfg.max_per_guild {
+		return Err(UploadError::Full);
+	}
+
+	if u64::from(attachment.size) > cfg.max_bytes {
+		return Err(UploadError::TooLarge);
+	}
+
+	let ext = Path::new(&attachment.filename)
+		.extension()
+		.and_then(|e| e.to_str())
+		.map(str::to_lowercase)
+		.filter(|e| !e.is_empty() && e.len() <= 5 && e.chars().all(|c| c.is_ascii_alphanumeric()))
+		.ok_or(UploadError::Unsupported)?;
+
+	let bytes = attachment
+		.download()
+		.await
+		.map_err(|_| UploadError::Download)?;
+
+	let duration = validate(&bytes, Some(&ext), cfg)?;
+
+	let file = file_path(cfg, guild_id, &name, &ext);
+	save(&file, &bytes).await?;
+
+	let sfx = CustomSfx {
+		name,
+		file,
+		duration,
+		size: bytes.len() as u64,
+		uploader,
+	};
+
+	let old = {
+		let mut lock = state.write().await;
+		lock.add_custom_sfx(sfx.clone()).await
+	};
+
+	add_resource(resources, &sfx.file);
+
+	if let Some(old) = old.filter(|old| old.file != sfx.file) {
+		forget(resources, &old).await;
+	}
+
+	Ok(sfx)
+}
+
+async fn forget(resources: &RxMap, sfx: &CustomSfx) {
+	resources.remove(&sfx.file);
+
+	if let Err(e) = fs::remove_file(&sfx.file).await {
+		warn!("Couldn't delete custom SFX file {}: {:?}", sfx.file, e);
+	}
+}
+
+#[command]
+#[aliases("sfx-list")]
+#[description = "Mrr? (What sounds has this server taught me?)"]
+#[owner_privilege]
+pub async fn sfx_list(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let mut builder = MessageBuilder::new();
+
+	if let Some(state) = gs.get(&guild_id) {
+		let lock = state.read().await;
+
+		if lock.custom_sfx().is_empty() {
+			builder.push_line("Nya... (Nobody's taught me any sounds here yet!)");
+		} else {
+			builder.push_bold_line("Sounds I know here:");
+			for sfx in lock.custom_sfx() {
+				builder.push_safe(&sfx.name);
+				builder.push_italic_line(format!(
+					" -- {:.1}s, {} bytes",
+					sfx.duration.as_secs_f64(),
+					sfx.size
+				));
+			}
+		}
+	}
+
+	check_msg(msg.channel_id.say(&ctx.http, builder.build()).await);
+
+	Ok(())
+}
+
+#[command]
+#[aliases("sfx-remove")]
+#[description = "Mya!? (Forget one of this server's sounds.)"]
+#[owner_privilege]
+pub async fn sfx_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let name = match args.single::<String>() {
+		Ok(n) => n.to_lowercase(),
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let (gs, resources) = {
+		let data = ctx.data.read().await;
+		(
+			Arc::clone(data.get::<GuildStates>().unwrap()),
+			Arc::clone(data.get::<Resources>().unwrap()),
+		)
+	};
+
+	let removed = if let Some(state) = gs.get(&guild_id) {
+		let mut lock = state.write().await;
+		lock.remove_custom_sfx(&name).await
+	} else {
+		None
+	};
+
+	let reply = match removed {
+		Some(sfx) => {
+			forget(&resources, &sfx).await;
+			format!("Mrr... (Forgot {:?}.)", sfx.name)
+		},
+		None => "Myeh? (I don't knyow that one.)".to_string(),
+	};
+
+	check_msg(msg.channel_id.say(&ctx.http, reply).await);
+
+	Ok(())
+}
+
+#[command]
+#[aliases("sfx-preview")]
+#[description = "Mrrp! (Hear one of this server's sounds.)"]
+#[owner_privilege]
+pub async fn sfx_preview(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(c) => c,
+		None => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let name = match args.single::<String>() {
+		Ok(n) => n.to_lowercase(),
+		Err(_) => {
+			return confused(ctx, msg).await;
+		},
+	};
+
+	let gs = {
+		let data = ctx.data.read().await;
+		Arc::clone(data.get::<GuildStates>().unwrap())
+	};
+
+	let sfx = if let Some(state) = gs.get(&guild_id) {
+		let lock = state.read().await;
+		lock.custom_sfx().iter().find(|s| s.name == name).cloned()
+	} else {
+		None
+	};
+
+	let sfx = match sfx {
+		Some(s) => s,
+		None => {
+			check_msg(
+				msg.channel_id
+					.say(&ctx.http, "Myeh? (I don't knyow that one.)")
+					.await,
+			);
+			return Ok(());
+		},
+	};
+
+	match CreateAttachment::path(&sfx.file).await {
+		Ok(file) => check_msg(
+			msg.channel_id
+				.send_message(
+					&ctx.http,
+					CreateMessage::new()
+						.content(format!(
+							"{} ({:.1}s)",
+							sfx.name,
+							sfx.duration.as_secs_f64()
+						))
+						.add_file(file),
+				)
+				.await,
+		),
+		Err(e) => {
+			error!("Couldn't read custom SFX file {}: {:?}", sfx.file, e);
+			check_msg(
+				msg.channel_id
+					.say(&ctx.http, "Nya?! (I seem to have lost that one...)")
+					.await,
+			);
+		},
+	}
+
+	Ok(())
+}
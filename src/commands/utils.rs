@@ -22,6 +22,29 @@ pub fn parse_chan_mention(args: &mut Args) -> Option<ChannelId> {
 	})
 }
 
+/// Reads an on/off style switch, e.g. `on`, `false` or `yes`.
+pub fn parse_flag(value: &str) -> Option<bool> {
+	match value {
+		"on" | "true" | "yes" => Some(true),
+		"off" | "false" | "no" => Some(false),
+		_ => None,
+	}
+}
+
+pub fn parse_user_mention(args: &mut Args) -> Option<UserId> {
+	let user_id = args.single::<String>().ok()?;
+
+	serenity::utils::parse_user_mention(user_id.as_str()).or_else(|| {
+		user_id.parse::<u64>().ok().and_then(|v| {
+			if v == 0 {
+				None
+			} else {
+				Some(UserId::new(v))
+			}
+		})
+	})
+}
+
 pub async fn confused(ctx: &Context, msg: &Message) -> CommandResult {
 	check_msg(msg.reply(ctx, "???").await);
 	Ok(())
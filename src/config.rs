@@ -1,13 +1,20 @@
+use crate::{
+	constants::{CUSTOM_SFX_DIR, SOUNDSCAPE_DIR, SWITCH_MAX_MARGIN, SWITCH_MAX_SECS, TRACE_DIR},
+	soundscape::SoundClass,
+};
 use enum_primitive::*;
 use serde::{Deserialize, Serialize};
 use serenity::{
 	client::Context,
 	framework::standard::Args,
 	model::{
-		id::{GuildId, RoleId},
+		id::{ChannelId, GuildId, RoleId},
+		mention::Mentionable,
 		user::User,
+		voice::VoiceState,
 	},
 };
+use std::{collections::HashSet, time::Duration};
 use tokio_postgres::Row;
 
 enum_from_primitive! {
@@ -203,6 +210,331 @@ impl Default for OptInOut {
 	}
 }
 
+/// Where (and how) to tell a server's members that Felyne is measuring their call.
+#[derive(Clone, Debug)]
+pub struct ConsentNotice {
+	pub channel: ChannelId,
+	pub template: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum NoticeStatus {
+	Started,
+	Paused,
+	Stopped,
+}
+
+impl NoticeStatus {
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Started => "started measuring",
+			Self::Paused => "paused measuring",
+			Self::Stopped => "stopped measuring",
+		}
+	}
+}
+
+impl ConsentNotice {
+	/// `{status}` and `{channel}` are filled in when a notice is posted.
+	pub const DEFAULT_TEMPLATE: &'static str = "Mya! (I've {status} voice traffic in {channel}.)";
+
+	pub fn from_row(row: &Row) -> Option<Self> {
+		let channel: Option<i64> = row.get(0);
+
+		channel.filter(|c| *c != 0).map(|c| Self {
+			channel: ChannelId::new(c as u64),
+			template: row.get(1),
+		})
+	}
+
+	pub fn render(&self, status: NoticeStatus, voice_channel: ChannelId, prefix: &str) -> String {
+		let body = self
+			.template
+			.as_deref()
+			.unwrap_or(Self::DEFAULT_TEMPLATE)
+			.replace("{status}", status.as_str())
+			.replace("{channel}", &voice_channel.mention().to_string());
+
+		format!(
+			"{}\n*Don't want to be measured? Use `{}optout`.*",
+			body, prefix
+		)
+	}
+}
+
+/// Voice channels a server wants Felyne to hunt in (if any are listed), and
+/// those she should stay out of.
+#[derive(Clone, Debug, Default)]
+pub struct ChannelFilter {
+	pub allow: HashSet<ChannelId>,
+	pub deny: HashSet<ChannelId>,
+}
+
+impl ChannelFilter {
+	pub fn from_rows(rows: &[Row]) -> Self {
+		let mut out = Self::default();
+
+		for row in rows {
+			let channel: i64 = row.get(0);
+			let allow: bool = row.get(1);
+			out.set(ChannelId::new(channel as u64), Some(allow));
+		}
+
+		out
+	}
+
+	/// Allows (`Some(true)`), denies (`Some(false)`), or forgets a channel.
+	pub fn set(&mut self, channel: ChannelId, allow: Option<bool>) {
+		self.allow.remove(&channel);
+		self.deny.remove(&channel);
+
+		match allow {
+			Some(true) => self.allow.insert(channel),
+			Some(false) => self.deny.insert(channel),
+			None => false,
+		};
+	}
+
+	pub fn permits(&self, channel: ChannelId) -> bool {
+		!self.deny.contains(&channel) && (self.allow.is_empty() || self.allow.contains(&channel))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.allow.is_empty() && self.deny.is_empty()
+	}
+}
+
+/// Which voice users count towards a channel's population when hunting
+/// autonomously, and how many are needed before Felyne will join.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PopulationRules {
+	pub ignore_bots: bool,
+	pub ignore_deafened: bool,
+	pub ignore_muted: bool,
+	pub min_users: u64,
+}
+
+impl Default for PopulationRules {
+	fn default() -> Self {
+		Self {
+			ignore_bots: true,
+			ignore_deafened: true,
+			ignore_muted: false,
+			min_users: 1,
+		}
+	}
+}
+
+impl PopulationRules {
+	pub fn from_row(row: &Row) -> Self {
+		let min_users: i32 = row.get(3);
+
+		Self {
+			ignore_bots: row.get(0),
+			ignore_deafened: row.get(1),
+			ignore_muted: row.get(2),
+			min_users: min_users.max(1) as u64,
+		}
+	}
+
+	pub fn counts(&self, state: &VoiceState, bot: bool) -> bool {
+		!((self.ignore_bots && bot)
+			|| (self.ignore_deafened && (state.deaf || state.self_deaf))
+			|| (self.ignore_muted && state.self_mute))
+	}
+}
+
+/// How reluctant Felyne is to move between channels by herself.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SwitchRules {
+	/// Seconds to stay in a channel before moving elsewhere.
+	pub dwell_secs: u64,
+	/// How many more users another channel needs than the current one.
+	pub margin: u64,
+	/// Seconds another channel must stay busiest before moving there.
+	pub debounce_secs: u64,
+}
+
+impl Default for SwitchRules {
+	fn default() -> Self {
+		Self {
+			dwell_secs: 30,
+			margin: 1,
+			debounce_secs: 5,
+		}
+	}
+}
+
+impl SwitchRules {
+	pub fn from_row(row: &Row) -> Self {
+		let dwell_secs: i32 = row.get(0);
+		let margin: i32 = row.get(1);
+		let debounce_secs: i32 = row.get(2);
+
+		Self {
+			dwell_secs: dwell_secs.max(0) as u64,
+			margin: margin.max(0) as u64,
+			debounce_secs: debounce_secs.max(0) as u64,
+		}
+		.bounded()
+	}
+
+	/// Pulls each setting back within `SWITCH_MAX_SECS` and `SWITCH_MAX_MARGIN`.
+	pub fn bounded(self) -> Self {
+		Self {
+			dwell_secs: self.dwell_secs.min(SWITCH_MAX_SECS),
+			margin: self.margin.min(SWITCH_MAX_MARGIN),
+			debounce_secs: self.debounce_secs.min(SWITCH_MAX_SECS),
+		}
+	}
+}
+
+/// Which join/leave cues Felyne plays, and how often.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReactionRules {
+	pub on_join: bool,
+	pub on_leave: bool,
+	/// Seconds to wait after one cue before playing another.
+	pub cooldown_secs: u64,
+}
+
+impl Default for ReactionRules {
+	fn default() -> Self {
+		Self {
+			on_join: true,
+			on_leave: true,
+			cooldown_secs: 15,
+		}
+	}
+}
+
+impl ReactionRules {
+	pub fn from_row(row: &Row) -> Self {
+		let cooldown_secs: i32 = row.get(2);
+
+		Self {
+			on_join: row.get(0),
+			on_leave: row.get(1),
+			cooldown_secs: cooldown_secs.max(0) as u64,
+		}
+	}
+}
+
+/// Felyne's overall volume in a server, and how loud each class of sound is
+/// relative to that.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlaybackPrefs {
+	pub volume: f32,
+	pub bgm: f32,
+	pub ambience: f32,
+	pub sfx: f32,
+}
+
+impl Default for PlaybackPrefs {
+	fn default() -> Self {
+		Self {
+			volume: 1.0,
+			bgm: 1.0,
+			ambience: 1.0,
+			sfx: 1.0,
+		}
+	}
+}
+
+impl PlaybackPrefs {
+	pub fn from_row(row: &Row) -> Self {
+		Self {
+			volume: row.get(0),
+			bgm: row.get(1),
+			ambience: row.get(2),
+			sfx: row.get(3),
+		}
+	}
+
+	pub fn multiplier(&self, class: SoundClass) -> f32 {
+		match class {
+			SoundClass::Bgm => self.bgm,
+			SoundClass::Ambience => self.ambience,
+			SoundClass::Sfx => self.sfx,
+		}
+	}
+
+	pub fn multiplier_mut(&mut self, class: SoundClass) -> &mut f32 {
+		match class {
+			SoundClass::Bgm => &mut self.bgm,
+			SoundClass::Ambience => &mut self.ambience,
+			SoundClass::Sfx => &mut self.sfx,
+		}
+	}
+}
+
+/// How Felyne lowers her volume while people in her channel are talking.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DuckRules {
+	pub enabled: bool,
+	/// Fraction of normal volume to play at while someone speaks.
+	pub depth: f32,
+	/// Milliseconds taken to duck once speech starts.
+	pub attack_ms: u64,
+	/// Milliseconds taken to recover once speech stops.
+	pub release_ms: u64,
+	/// Hold back new sound effects until nobody is talking.
+	pub hold_sfx: bool,
+}
+
+impl Default for DuckRules {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			depth: 0.4,
+			attack_ms: 150,
+			release_ms: 1_000,
+			hold_sfx: false,
+		}
+	}
+}
+
+impl DuckRules {
+	pub fn from_row(row: &Row) -> Self {
+		let depth: f32 = row.get(1);
+		let attack_ms: i32 = row.get(2);
+		let release_ms: i32 = row.get(3);
+
+		Self {
+			enabled: row.get(0),
+			depth: depth.clamp(0.0, 1.0),
+			attack_ms: attack_ms.max(0) as u64,
+			release_ms: release_ms.max(0) as u64,
+			hold_sfx: row.get(4),
+		}
+	}
+}
+
+/// How long Felyne stays in a channel with nobody talking (or nobody able to
+/// hear her) before she dozes off.
+///
+/// Once asleep, only a voice state change from someone in a voice channel who
+/// isn't deafened (or a bot), or being sent somewhere, wakes her up.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IdleRules {
+	/// Minutes of quiet before she leaves; zero (the default) keeps her around forever.
+	pub timeout_mins: u64,
+}
+
+impl IdleRules {
+	pub fn from_row(row: &Row) -> Self {
+		let timeout_mins: i32 = row.get(0);
+
+		Self {
+			timeout_mins: timeout_mins.max(0) as u64,
+		}
+	}
+
+	pub fn timeout(&self) -> Option<Duration> {
+		Some(Duration::from_secs(self.timeout_mins.saturating_mul(60))).filter(|t| !t.is_zero())
+	}
+}
+
 enum_from_primitive! {
 #[derive(Clone, Copy, Debug)]
 pub enum ControlMode {
@@ -332,6 +664,60 @@ pub enum ConfigParseError {
 pub struct BotConfig {
 	pub database: DatabaseConfig,
 	pub token: String,
+	#[serde(default)]
+	pub traces: TraceConfig,
+	#[serde(default = "BotConfig::default_soundscape_dir")]
+	pub soundscape_dir: String,
+	#[serde(default)]
+	pub custom_sfx: CustomSfxConfig,
+}
+
+impl BotConfig {
+	fn default_soundscape_dir() -> String {
+		SOUNDSCAPE_DIR.to_string()
+	}
+}
+
+/// Limits on the sound effects each server can upload.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CustomSfxConfig {
+	#[serde(default = "CustomSfxConfig::default_dir")]
+	pub dir: String,
+	#[serde(default = "CustomSfxConfig::default_max_bytes")]
+	pub max_bytes: u64,
+	#[serde(default = "CustomSfxConfig::default_max_duration_ms")]
+	pub max_duration_ms: u64,
+	#[serde(default = "CustomSfxConfig::default_max_per_guild")]
+	pub max_per_guild: usize,
+}
+
+impl CustomSfxConfig {
+	fn default_dir() -> String {
+		CUSTOM_SFX_DIR.to_string()
+	}
+
+	fn default_max_bytes() -> u64 {
+		1 << 20
+	}
+
+	fn default_max_duration_ms() -> u64 {
+		10_000
+	}
+
+	fn default_max_per_guild() -> usize {
+		25
+	}
+}
+
+impl Default for CustomSfxConfig {
+	fn default() -> Self {
+		Self {
+			dir: Self::default_dir(),
+			max_bytes: Self::default_max_bytes(),
+			max_duration_ms: Self::default_max_duration_ms(),
+			max_per_guild: Self::default_max_per_guild(),
+		}
+	}
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -341,3 +727,162 @@ pub struct DatabaseConfig {
 	pub host: String,
 	pub port: Option<u16>,
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TraceConfig {
+	#[serde(default)]
+	pub sink: TraceSinkConfig,
+	#[serde(default = "TraceConfig::default_retries")]
+	pub retries: u32,
+	#[serde(default = "TraceConfig::default_retry_backoff_ms")]
+	pub retry_backoff_ms: u64,
+	#[serde(default)]
+	pub retention: RetentionConfig,
+}
+
+impl TraceConfig {
+	fn default_retries() -> u32 {
+		3
+	}
+
+	fn default_retry_backoff_ms() -> u64 {
+		500
+	}
+}
+
+impl Default for TraceConfig {
+	fn default() -> Self {
+		Self {
+			sink: Default::default(),
+			retries: Self::default_retries(),
+			retry_backoff_ms: Self::default_retry_backoff_ms(),
+			retention: Default::default(),
+		}
+	}
+}
+
+/// Limits on how many traces are kept. Unset limits are not enforced.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RetentionConfig {
+	pub max_age_secs: Option<u64>,
+	pub max_total_bytes: Option<u64>,
+	pub max_files: Option<usize>,
+	/// Move pruned traces into this directory, rather than deleting them.
+	pub archive_dir: Option<String>,
+	/// Stop capturing new traces when over quota, rather than pruning the oldest.
+	#[serde(default)]
+	pub refuse_when_full: bool,
+	#[serde(default = "RetentionConfig::default_interval_secs")]
+	pub interval_secs: u64,
+}
+
+impl RetentionConfig {
+	fn default_interval_secs() -> u64 {
+		3600
+	}
+
+	pub fn is_enforced(&self) -> bool {
+		self.max_age_secs.is_some() || self.max_total_bytes.is_some() || self.max_files.is_some()
+	}
+
+	pub fn over_quota(&self, files: usize, bytes: u64) -> bool {
+		self.max_files.map(|max| files > max).unwrap_or(false)
+			|| self.max_total_bytes.map(|max| bytes > max).unwrap_or(false)
+	}
+}
+
+impl Default for RetentionConfig {
+	fn default() -> Self {
+		Self {
+			max_age_secs: None,
+			max_total_bytes: None,
+			max_files: None,
+			archive_dir: None,
+			refuse_when_full: false,
+			interval_secs: Self::default_interval_secs(),
+		}
+	}
+}
+
+/// Where finished traces are written to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TraceSinkConfig {
+	Fs {
+		#[serde(default = "TraceSinkConfig::default_dir")]
+		dir: String,
+	},
+	Postgres,
+	S3(S3Config),
+}
+
+impl TraceSinkConfig {
+	fn default_dir() -> String {
+		TRACE_DIR.to_string()
+	}
+}
+
+impl Default for TraceSinkConfig {
+	fn default() -> Self {
+		Self::Fs {
+			dir: Self::default_dir(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct S3Config {
+	pub endpoint: String,
+	pub bucket: String,
+	#[serde(default = "S3Config::default_region")]
+	pub region: String,
+	pub access_key: String,
+	pub secret_key: String,
+	#[serde(default)]
+	pub prefix: String,
+}
+
+impl S3Config {
+	fn default_region() -> String {
+		"us-east-1".to_string()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_channel_filter_permits() {
+		let (a, b, c) = (ChannelId::new(1), ChannelId::new(2), ChannelId::new(3));
+		let mut filter = ChannelFilter::default();
+
+		filter.set(a, Some(false));
+		assert!(!filter.permits(a));
+		assert!(filter.permits(b));
+
+		filter.set(b, Some(true));
+		assert!(filter.permits(b));
+		assert!(!filter.permits(c));
+
+		filter.set(b, None);
+		filter.set(a, None);
+		assert!(filter.is_empty());
+		assert!(filter.permits(c));
+	}
+	#[test]
+	fn test_population_rules_ignore_deafened_by_default() {
+		let rules = PopulationRules::default();
+		let mut state: VoiceState = serde_json::from_str(
+			r#"{"channel_id":null,"deaf":false,"mute":false,"self_deaf":false,"self_mute":true,
+			"self_video":false,"session_id":"","suppress":false,"user_id":"1"}"#,
+		)
+		.unwrap();
+
+		assert!(rules.counts(&state, false));
+		assert!(!rules.counts(&state, true));
+
+		state.self_deaf = true;
+		assert!(!rules.counts(&state, false));
+	}
+}
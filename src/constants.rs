@@ -7,42 +7,34 @@ pub const MONSTERS: &[&str] = &[
 	"Deviljho",
 ];
 pub const BACKUP_SIZE: usize = 500;
+/// Milliseconds between steps of a fade or duck, and the soonest Felyne will
+/// look at her sound machines again.
 pub const VOICEHUNT_FRAME_TIME: u64 = 20;
-
-pub const SFX: &[&str] = &[
-	"aggro1.opus",
-	"dig1.opus",
-	"dig-hit1.opus",
-	"hit1.opus",
-	"maow1.opus",
-	"mewl1.opus",
-	"mewl2.opus",
-	"mewl3.opus",
-	"mewl4.opus",
-	"mewl5.opus",
-	"mewl6.opus",
-	"mewl7.opus",
-	"mewl-wiggle1.opus",
-	"mewl-wiggle2.opus",
-	"recover1.opus",
-	"wiggle1.opus",
-];
-
-pub const BONUS_SFX: &[&str] = &["gargwa1.opus", "gargwa2.opus", "gargwa3.opus"];
-
-pub const BBQ: &[&str] = &["5796.opus"];
-
-pub const BBQ_RESULT: &[&str] = &["5797.opus", "5798.opus"];
-
-pub const SLEEP: &[&str] = &["5799.opus"];
-
-pub const START: &[&str] = &["5839.opus", "5840.opus", "5841.opus", "5842.opus"];
-
-pub const AMBIENCE: &[&str] = &[
-	"5824.opus", "5825.opus", "5826.opus", "5827.opus", "5828.opus", "5829.opus", "5830.opus", "5831.opus",
-	"5832.opus",
-];
-
-pub const BGM: &[&str] = &["5815.opus", "5816.opus", "5817.opus", "6383.opus", "6384.opus"];
+pub const BGM_QUEUE_LENGTH: usize = 8;
+pub const BGM_HISTORY_LENGTH: usize = 20;
+pub const STATUS_TIMEOUT: u64 = 5;
+/// Milliseconds before Felyne first tries to get back into a channel she's
+/// dropped out of, doubling after each failure up to `VOICEHUNT_REJOIN_MAX_MS`.
+pub const VOICEHUNT_REJOIN_MS: u64 = 1_000;
+pub const VOICEHUNT_REJOIN_MAX_MS: u64 = 30_000;
+pub const VOICEHUNT_REJOIN_ATTEMPTS: u32 = 5;
+/// Failures after which a sound is dropped from `Resources` until they're next loaded.
+pub const TRACK_QUARANTINE_FAILURES: u32 = 3;
+/// Longest Felyne can be told to dwell in a channel, or wait out a busier one.
+pub const SWITCH_MAX_SECS: u64 = 24 * 60 * 60;
+pub const SWITCH_MAX_MARGIN: u64 = 1_000;
+pub const REACTION_MAX_COOLDOWN_SECS: u64 = 24 * 60 * 60;
+/// Longest idle timeout a server can set: a week.
+pub const IDLE_MAX_MINUTES: u64 = 7 * 24 * 60;
+pub const SCHEDULE_TICK_SECS: u64 = 30;
+pub const SPEECH_HOLD_MS: u64 = 200;
+pub const QUEST_MAX_MINUTES: u64 = 720;
+/// Minutes left at which a quest warns its party, unless told otherwise.
+pub const QUEST_WARNINGS: &[u64] = &[10, 5, 1];
+pub const SHUTDOWN_OUTRO_TIMEOUT: u64 = 20;
+pub const SHUTDOWN_TRACE_TIMEOUT: u64 = 30;
 
 pub const TRACE_DIR: &str = "traces/";
+pub const SOUNDSCAPE_DIR: &str = "soundscapes/";
+pub const DEFAULT_SOUNDSCAPE: &str = "default";
+pub const CUSTOM_SFX_DIR: &str = "custom-sfx/";
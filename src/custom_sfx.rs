@@ -0,0 +1,182 @@
+use crate::{audio_resources::measure, config::CustomSfxConfig};
+use serenity::{
+	model::prelude::{GuildId, UserId},
+	prelude::TypeMapKey,
+};
+use std::{io::Cursor, path::Path, sync::Arc, time::Duration};
+use tokio::fs;
+use tokio_postgres::Row;
+
+pub struct CustomSfxSettings;
+
+impl TypeMapKey for CustomSfxSettings {
+	type Value = Arc<CustomSfxConfig>;
+}
+
+/// A sound effect uploaded by a server's admins.
+#[derive(Clone, Debug)]
+pub struct CustomSfx {
+	pub name: String,
+	/// Path on disk, which also keys the sound in the `RxMap`.
+	pub file: String,
+	pub duration: Duration,
+	pub size: u64,
+	pub uploader: UserId,
+}
+
+impl CustomSfx {
+	pub fn from_row(row: &Row) -> Self {
+		let duration_ms: i64 = row.get(2);
+		let size: i64 = row.get(3);
+		let uploader: i64 = row.get(4);
+
+		Self {
+			name: row.get(0),
+			file: row.get(1),
+			duration: Duration::from_millis(duration_ms as u64),
+			size: size as u64,
+			uploader: UserId::new(uploader as u64),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+	BadName,
+	Full,
+	TooLarge,
+	TooLong(Duration),
+	Unsupported,
+	Download,
+	Io(std::io::Error),
+}
+
+impl UploadError {
+	pub fn explain(&self, cfg: &CustomSfxConfig) -> String {
+		match self {
+			Self::BadName =>
+				"Mya? Names should be 1-32 letters, numbers, `-` or `_`.".to_string(),
+			Self::Full => format!(
+				"Mrowr! This server already has {} sounds: remove one first!",
+				cfg.max_per_guild
+			),
+			Self::TooLarge => format!(
+				"Hiss! That's too big! Keep it under {} bytes.",
+				cfg.max_bytes
+			),
+			Self::TooLong(d) => format!(
+				"Nyaa... that's {:.1}s long! Keep it under {:.1}s.",
+				d.as_secs_f64(),
+				Duration::from_millis(cfg.max_duration_ms).as_secs_f64()
+			),
+			Self::Unsupported => "Myeh? I can't play that kind of file.".to_string(),
+			Self::Download => "Nya?! I couldn't fetch that attachment.".to_string(),
+			Self::Io(_) => "Mrrp... I couldn't save that. Try again later?".to_string(),
+		}
+	}
+}
+
+impl From<std::io::Error> for UploadError {
+	fn from(e: std::io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+pub fn valid_name(name: &str) -> bool {
+	(1..=32).contains(&name.len())
+		&& name
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Checks that Felyne can decode an upload, and that it isn't too long.
+pub fn validate(
+	bytes: &[u8],
+	extension: Option<&str>,
+	cfg: &CustomSfxConfig,
+) -> Result<Duration, UploadError> {
+	if bytes.len() as u64 > cfg.max_bytes {
+		return Err(UploadError::TooLarge);
+	}
+
+	let duration = measure(Box::new(Cursor::new(bytes.to_vec())), extension)
+		.ok_or(UploadError::Unsupported)?;
+
+	if duration > Duration::from_millis(cfg.max_duration_ms) {
+		Err(UploadError::TooLong(duration))
+	} else {
+		Ok(duration)
+	}
+}
+
+pub fn file_path(cfg: &CustomSfxConfig, guild_id: GuildId, name: &str, ext: &str) -> String {
+	format!(
+		"{}/{}/{}.{}",
+		cfg.dir.trim_end_matches('/'),
+		guild_id,
+		name,
+		ext
+	)
+}
+
+pub async fn save(path: &str, bytes: &[u8]) -> Result<(), UploadError> {
+	if let Some(dir) = Path::new(path).parent() {
+		fs::create_dir_all(dir).await?;
+	}
+
+	fs::write(path, bytes).await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn wav(secs: u32) -> Vec<u8> {
+		let rate: u32 = 8_000;
+		let data_len = rate * secs * 2;
+
+		let mut out = Vec::new();
+		out.extend_from_slice(b"RIFF");
+		out.extend_from_slice(&(36 + data_len).to_le_bytes());
+		out.extend_from_slice(b"WAVEfmt ");
+		out.extend_from_slice(&16u32.to_le_bytes());
+		out.extend_from_slice(&1u16.to_le_bytes());
+		out.extend_from_slice(&1u16.to_le_bytes());
+		out.extend_from_slice(&rate.to_le_bytes());
+		out.extend_from_slice(&(rate * 2).to_le_bytes());
+		out.extend_from_slice(&2u16.to_le_bytes());
+		out.extend_from_slice(&16u16.to_le_bytes());
+		out.extend_from_slice(b"data");
+		out.extend_from_slice(&data_len.to_le_bytes());
+		out.resize(out.len() + data_len as usize, 0);
+		out
+	}
+
+	#[test]
+	fn test_validate_measures_duration() {
+		let cfg = CustomSfxConfig::default();
+
+		let duration = validate(&wav(2), Some("wav"), &cfg).unwrap();
+
+		assert_eq!(duration, Duration::from_secs(2));
+	}
+
+	#[test]
+	fn test_validate_rejects_long_and_garbage() {
+		let cfg = CustomSfxConfig {
+			max_duration_ms: 1_000,
+			..Default::default()
+		};
+
+		assert!(matches!(
+			validate(&wav(2), Some("wav"), &cfg),
+			Err(UploadError::TooLong(_))
+		));
+		assert!(matches!(
+			validate(b"not audio at all", None, &cfg),
+			Err(UploadError::Unsupported)
+		));
+	}
+}
@@ -1,6 +1,13 @@
 mod query;
 
-use crate::{config::*, server::*, voicehunt::mode::Join};
+use crate::{
+	config::*,
+	custom_sfx::CustomSfx,
+	schedule::ScheduleEntry,
+	server::*,
+	trace_sink::{StoredTrace, TraceManifest},
+	voicehunt::mode::Join,
+};
 
 use enum_primitive::FromPrimitive;
 use query::Query;
@@ -31,7 +38,7 @@ pub async fn init_db_tables(db: &Client) -> Result<HashMap<Query, Statement>, Sq
 
 	let mut out = HashMap::new();
 
-	for i in 0..=(Query::DeleteOptOut as u32) {
+	for i in 0..=(Query::UpsertIdle as u32) {
 		let query_type = Query::from_u32(i).unwrap();
 		let query_dir = query_type.query_dir();
 		let data = fs::read_to_string(&query_dir).await.unwrap_or_else(|_| {
@@ -156,6 +163,398 @@ impl FelyneDb {
 		}
 	}
 
+	#[inline]
+	pub async fn select_soundscape(&self, guild_id: GuildId) -> Result<String, SqlError> {
+		let t_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectSoundscape);
+
+		self.db.query_one(&query, &[&t_id]).await.map(move |row| {
+			let a: String = row.get(0);
+			a
+		})
+	}
+
+	#[inline]
+	pub async fn upsert_soundscape(&self, guild_id: GuildId, name: &str) {
+		let t_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpsertSoundscape);
+
+		let val = self.db.execute(&query, &[&t_id, &name]).await;
+
+		if let Err(e) = val {
+			error!(
+				"Nya?! (Couldn't write soundscape_config db updates.){:?}",
+				e
+			);
+		}
+	}
+
+	#[inline]
+	pub async fn select_guild_sfx(&self, guild_id: GuildId) -> Result<Vec<CustomSfx>, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectGuildSfx);
+
+		self.db
+			.query(&query, &[&g_id])
+			.await
+			.map(|rows| rows.iter().map(CustomSfx::from_row).collect())
+	}
+
+	#[inline]
+	pub async fn upsert_guild_sfx(&self, guild_id: GuildId, sfx: &CustomSfx) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpsertGuildSfx);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&sfx.name,
+					&sfx.file,
+					&(sfx.duration.as_millis() as i64),
+					&(sfx.size as i64),
+					&i64::from(sfx.uploader),
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write guild_sfx db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn delete_guild_sfx(&self, guild_id: GuildId, name: &str) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::DeleteGuildSfx);
+
+		let val = self.db.execute(&query, &[&g_id, &name]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write guild_sfx db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_channel_filter(
+		&self,
+		guild_id: GuildId,
+	) -> Result<ChannelFilter, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectChannelFilter);
+
+		self.db
+			.query(&query, &[&g_id])
+			.await
+			.map(|rows| ChannelFilter::from_rows(&rows))
+	}
+
+	#[inline]
+	pub async fn upsert_channel_filter(&self, guild_id: GuildId, channel: ChannelId, allow: bool) {
+		let g_id = i64::from(guild_id);
+		let c_id = i64::from(channel);
+
+		let query = self.get_statement(Query::UpsertChannelFilter);
+
+		let val = self.db.execute(&query, &[&g_id, &c_id, &allow]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write channel_filter db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn delete_channel_filter(&self, guild_id: GuildId, channel: ChannelId) {
+		let g_id = i64::from(guild_id);
+		let c_id = i64::from(channel);
+
+		let query = self.get_statement(Query::DeleteChannelFilter);
+
+		let val = self.db.execute(&query, &[&g_id, &c_id]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write channel_filter db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_population_rules(
+		&self,
+		guild_id: GuildId,
+	) -> Result<PopulationRules, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectPopulation);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| PopulationRules::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_population_rules(&self, guild_id: GuildId, rules: PopulationRules) {
+		let g_id = i64::from(guild_id);
+		let min_users = rules.min_users.min(i32::MAX as u64) as i32;
+
+		let query = self.get_statement(Query::UpsertPopulation);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&rules.ignore_bots,
+					&rules.ignore_deafened,
+					&rules.ignore_muted,
+					&min_users,
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!(
+				"Nya?! (Couldn't write population_config db updates.){:?}",
+				e
+			);
+		}
+	}
+
+	#[inline]
+	pub async fn select_switch_rules(&self, guild_id: GuildId) -> Result<SwitchRules, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectSwitch);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| SwitchRules::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_switch_rules(&self, guild_id: GuildId, rules: SwitchRules) {
+		let g_id = i64::from(guild_id);
+		let clamp = |v: u64| v.min(i32::MAX as u64) as i32;
+
+		let query = self.get_statement(Query::UpsertSwitch);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&clamp(rules.dwell_secs),
+					&clamp(rules.margin),
+					&clamp(rules.debounce_secs),
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write switch_config db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_reaction_rules(
+		&self,
+		guild_id: GuildId,
+	) -> Result<ReactionRules, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectReactions);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| ReactionRules::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_reaction_rules(&self, guild_id: GuildId, rules: ReactionRules) {
+		let g_id = i64::from(guild_id);
+		let cooldown_secs = rules.cooldown_secs.min(i32::MAX as u64) as i32;
+
+		let query = self.get_statement(Query::UpsertReactions);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[&g_id, &rules.on_join, &rules.on_leave, &cooldown_secs],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write reaction_config db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_duck_rules(&self, guild_id: GuildId) -> Result<DuckRules, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectDuck);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| DuckRules::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_duck_rules(&self, guild_id: GuildId, rules: DuckRules) {
+		let g_id = i64::from(guild_id);
+		let clamp = |v: u64| v.min(i32::MAX as u64) as i32;
+
+		let query = self.get_statement(Query::UpsertDuck);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&rules.enabled,
+					&rules.depth,
+					&clamp(rules.attack_ms),
+					&clamp(rules.release_ms),
+					&rules.hold_sfx,
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write duck_config db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_playback(&self, guild_id: GuildId) -> Result<PlaybackPrefs, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectPlayback);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| PlaybackPrefs::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_playback(&self, guild_id: GuildId, prefs: PlaybackPrefs) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpsertPlayback);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&prefs.volume,
+					&prefs.bgm,
+					&prefs.ambience,
+					&prefs.sfx,
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write playback_config db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_schedule(&self, guild_id: GuildId) -> Result<Vec<ScheduleEntry>, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectSchedule);
+
+		self.db
+			.query(&query, &[&g_id])
+			.await
+			.map(|rows| rows.iter().filter_map(ScheduleEntry::from_row).collect())
+	}
+
+	#[inline]
+	pub async fn upsert_schedule_entry(&self, guild_id: GuildId, entry: &ScheduleEntry) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpsertSchedule);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&(entry.id as i32),
+					&(entry.kind as i32),
+					&entry.days.bits(),
+					&entry.start_minute(),
+					&entry.end_minute(),
+					&entry.tz.name(),
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write schedule_entry db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn delete_schedule_entry(&self, guild_id: GuildId, id: u32) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::DeleteSchedule);
+
+		let val = self.db.execute(&query, &[&g_id, &(id as i32)]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write schedule_entry db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn select_idle_rules(&self, guild_id: GuildId) -> Result<IdleRules, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectIdle);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| IdleRules::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_idle_rules(&self, guild_id: GuildId, rules: IdleRules) {
+		let g_id = i64::from(guild_id);
+		let timeout_mins = rules.timeout_mins.min(i32::MAX as u64) as i32;
+
+		let query = self.get_statement(Query::UpsertIdle);
+
+		let val = self.db.execute(&query, &[&g_id, &timeout_mins]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write idle_config db updates.){:?}", e);
+		}
+	}
+
 	#[inline]
 	pub async fn select_optout_users(&self) -> Result<Vec<UserId>, SqlError> {
 		let query = self.get_statement(Query::SelectOptOuts);
@@ -247,6 +646,7 @@ impl FelyneDb {
 					&g_id,
 					&(mode.to_val()),
 					&(mode.to_channel().unwrap_or(0i64)),
+					&(mode.to_user().unwrap_or(0i64)),
 				],
 			)
 			.await;
@@ -352,6 +752,51 @@ impl FelyneDb {
 		}
 	}
 
+	#[inline]
+	pub async fn select_consent_notice(
+		&self,
+		guild_id: GuildId,
+	) -> Result<Option<ConsentNotice>, SqlError> {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::SelectNotice);
+
+		self.db
+			.query_one(&query, &[&g_id])
+			.await
+			.map(move |row| ConsentNotice::from_row(&row))
+	}
+
+	#[inline]
+	pub async fn upsert_consent_notice(
+		&self,
+		guild_id: GuildId,
+		opt: OptInOut,
+		notice: Option<&ConsentNotice>,
+	) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpsertNotice);
+
+		let val = self
+			.db
+			.execute(
+				&query,
+				&[
+					&g_id,
+					&(opt.to_val()),
+					&(opt.to_role().unwrap_or(0i64)),
+					&notice.map(|n| i64::from(n.channel)),
+					&notice.and_then(|n| n.template.as_ref()),
+				],
+			)
+			.await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write opt_in_out db updates.){:?}", e);
+		}
+	}
+
 	#[inline]
 	pub async fn select_server_type(&self, guild_id: GuildId) -> Result<Label, SqlError> {
 		let g_id = i64::from(guild_id);
@@ -465,4 +910,95 @@ impl FelyneDb {
 			error!("Nya?! (Couldn't write user_ack db updates.){:?}", e);
 		}
 	}
+
+	#[inline]
+	pub async fn upsert_trace(&self, key: &str, data: &[u8]) -> Result<(), SqlError> {
+		let query = self.get_statement(Query::UpsertTrace);
+
+		self.db.execute(&query, &[&key, &data]).await.map(|_| ())
+	}
+
+	#[inline]
+	pub async fn select_traces(&self) -> Result<Vec<StoredTrace>, SqlError> {
+		let query = self.get_statement(Query::SelectTraces);
+
+		self.db.query(&query, &[]).await.map(move |rows| {
+			rows.into_iter()
+				.map(|row| {
+					let size: i32 = row.get(1);
+					StoredTrace {
+						key: row.get(0),
+						size: size as u64,
+						created: row.get(2),
+					}
+				})
+				.collect()
+		})
+	}
+
+	#[inline]
+	pub async fn select_trace_data(&self, key: &str) -> Result<Vec<u8>, SqlError> {
+		let query = self.get_statement(Query::SelectTraceData);
+
+		self.db
+			.query_one(&query, &[&key])
+			.await
+			.map(move |row| row.get(0))
+	}
+
+	#[inline]
+	pub async fn delete_trace(&self, key: &str) -> Result<(), SqlError> {
+		let query = self.get_statement(Query::DeleteTrace);
+
+		self.db.execute(&query, &[&key]).await.map(|_| ())
+	}
+
+	#[inline]
+	pub async fn upsert_trace_manifest(&self, manifest: &TraceManifest) -> Result<(), SqlError> {
+		let query = self.get_statement(Query::UpsertManifest);
+
+		self.db
+			.execute(
+				&query,
+				&[
+					&manifest.key,
+					&manifest.label,
+					&manifest.length_ns,
+					&manifest.total_user_count,
+					&manifest.starting_user_count,
+					&manifest.optout_user_count,
+					&manifest.server,
+					&manifest.region,
+					&manifest.version,
+				],
+			)
+			.await
+			.map(|_| ())
+	}
+
+	#[inline]
+	pub async fn mark_guild_ack_used(&self, guild_id: GuildId) {
+		let g_id = i64::from(guild_id);
+
+		let query = self.get_statement(Query::UpdateGuildAck);
+
+		let val = self.db.execute(&query, &[&g_id]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write guild_ack db updates.){:?}", e);
+		}
+	}
+
+	#[inline]
+	pub async fn mark_user_acks_used(&self, user_ids: &[UserId]) {
+		let u_ids: Vec<i64> = user_ids.iter().map(|u| i64::from(*u)).collect();
+
+		let query = self.get_statement(Query::UpdateAck);
+
+		let val = self.db.execute(&query, &[&u_ids]).await;
+
+		if let Err(e) = val {
+			error!("Nya?! (Couldn't write user_ack db updates.){:?}", e);
+		}
+	}
 }
@@ -29,10 +29,12 @@ pub enum Query {
 	SelectAck,
 	UpsertAck,
 	DeleteAck,
+	UpdateAck,
 
 	SelectGuildAck,
 	UpsertGuildAck,
 	DeleteGuildAck,
+	UpdateGuildAck,
 
 	SelectLabel,
 	UpsertLabel,
@@ -41,6 +43,50 @@ pub enum Query {
 	SelectOptOuts,
 	UpsertOptOut,
 	DeleteOptOut,
+
+	UpsertTrace,
+
+	UpsertManifest,
+
+	SelectTraces,
+	DeleteTrace,
+	SelectTraceData,
+
+	SelectNotice,
+	UpsertNotice,
+
+	SelectSoundscape,
+	UpsertSoundscape,
+
+	SelectGuildSfx,
+	UpsertGuildSfx,
+	DeleteGuildSfx,
+
+	SelectChannelFilter,
+	UpsertChannelFilter,
+	DeleteChannelFilter,
+
+	SelectPopulation,
+	UpsertPopulation,
+
+	SelectSwitch,
+	UpsertSwitch,
+
+	SelectReactions,
+	UpsertReactions,
+
+	SelectDuck,
+	UpsertDuck,
+
+	SelectPlayback,
+	UpsertPlayback,
+
+	SelectSchedule,
+	UpsertSchedule,
+	DeleteSchedule,
+
+	SelectIdle,
+	UpsertIdle,
 }
 }
 
@@ -51,13 +97,20 @@ impl Query {
 		match self {
 			SelectAdminCtl | SelectVoiceCtl | SelectJoin | SelectGather | SelectPrefix
 			| SelectServerOptIn | SelectUndelete | SelectAck | SelectGuildAck | SelectLabel
-			| SelectOptOuts => "select",
+			| SelectOptOuts | SelectTraces | SelectTraceData | SelectNotice | SelectSoundscape
+			| SelectGuildSfx | SelectChannelFilter | SelectPopulation | SelectSwitch
+			| SelectReactions | SelectDuck | SelectPlayback | SelectSchedule | SelectIdle => "select",
 
 			UpsertAdminCtl | UpsertVoiceCtl | UpsertJoin | UpsertGather | UpsertPrefix
 			| UpsertServerOptIn | UpsertUndelete | UpsertAck | UpsertGuildAck | UpsertLabel
-			| UpsertOptOut => "upsert",
+			| UpsertOptOut | UpsertTrace | UpsertManifest | UpsertNotice | UpsertSoundscape
+			| UpsertGuildSfx | UpsertChannelFilter | UpsertPopulation | UpsertSwitch
+			| UpsertReactions | UpsertDuck | UpsertPlayback | UpsertSchedule | UpsertIdle => "upsert",
+
+			DeleteAck | DeleteGuildAck | DeleteLabel | DeleteOptOut | DeleteTrace
+			| DeleteGuildSfx | DeleteChannelFilter | DeleteSchedule => "delete",
 
-			DeleteAck | DeleteGuildAck | DeleteLabel | DeleteOptOut => "delete",
+			UpdateAck | UpdateGuildAck => "update",
 		}
 	}
 
@@ -79,13 +132,41 @@ impl Query {
 
 			SelectUndelete | UpsertUndelete => "undelete",
 
-			SelectAck | UpsertAck | DeleteAck => "ack",
+			SelectAck | UpsertAck | DeleteAck | UpdateAck => "ack",
 
-			SelectGuildAck | UpsertGuildAck | DeleteGuildAck => "gack",
+			SelectGuildAck | UpsertGuildAck | DeleteGuildAck | UpdateGuildAck => "gack",
 
 			SelectLabel | UpsertLabel | DeleteLabel => "label",
 
 			SelectOptOuts | UpsertOptOut | DeleteOptOut => "optout",
+
+			UpsertTrace | SelectTraces | DeleteTrace => "trace",
+
+			SelectTraceData => "tracedata",
+
+			SelectNotice | UpsertNotice => "notice",
+
+			SelectSoundscape | UpsertSoundscape => "soundscape",
+
+			SelectGuildSfx | UpsertGuildSfx | DeleteGuildSfx => "sfx",
+
+			SelectChannelFilter | UpsertChannelFilter | DeleteChannelFilter => "chanfilter",
+
+			SelectPopulation | UpsertPopulation => "population",
+
+			SelectSwitch | UpsertSwitch => "switch",
+
+			SelectReactions | UpsertReactions => "reactions",
+
+			SelectDuck | UpsertDuck => "duck",
+
+			SelectPlayback | UpsertPlayback => "playback",
+
+			SelectSchedule | UpsertSchedule | DeleteSchedule => "schedule",
+
+			SelectIdle | UpsertIdle => "idle",
+
+			UpsertManifest => "manifest",
 		}
 	}
 
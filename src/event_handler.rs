@@ -1,4 +1,6 @@
-use crate::{guild::*, voicehunt::*, watchcat::*, Db};
+use crate::{
+	audio_resources::*, guild::*, schedule::spawn_schedule_task, voicehunt::*, watchcat::*, Db,
+};
 
 use serenity::{async_trait, client::*, gateway::ActivityData, model::prelude::*};
 use std::sync::Arc;
@@ -57,9 +59,14 @@ impl EventHandler for FelyneEvts {
 			let datas = ctx.data.read().await;
 			let db = datas.get::<Db>().unwrap().clone();
 			let states = datas.get::<GuildStates>().unwrap();
+			let resources = datas.get::<Resources>().unwrap();
 
 			let state = GuildState::new(db, guild.id).await;
 
+			for sfx in state.custom_sfx() {
+				add_resource(resources, &sfx.file);
+			}
+
 			states.insert(guild.id, Arc::new(RwLock::new(state)));
 		}
 
@@ -80,5 +87,7 @@ impl EventHandler for FelyneEvts {
 	async fn ready(&self, ctx: Context, _rdy: Ready) {
 		println!("Connyected!");
 		ctx.set_activity(Some(ActivityData::listening("scary monsters!")));
+
+		spawn_schedule_task(ctx);
 	}
 }
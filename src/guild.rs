@@ -1,12 +1,20 @@
-use crate::{config::*, dbs::*, server::Label, voicehunt::mode::Join, UserStateKey};
+use crate::{
+	config::*,
+	custom_sfx::CustomSfx,
+	dbs::*,
+	schedule::ScheduleEntry,
+	server::Label,
+	voicehunt::mode::Join,
+	UserStateKey,
+};
 use dashmap::DashMap;
 use serenity::{
 	client::Context,
-	model::prelude::{ChannelId, GuildId, User},
+	model::prelude::{ChannelId, GuildId, Mentionable, User},
 	prelude::*,
 	utils::MessageBuilder,
 };
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 use tokio::sync::RwLock;
 
 pub type GuildMap = Arc<DashMap<GuildId, Arc<RwLock<GuildState>>>>;
@@ -23,12 +31,23 @@ pub struct GuildState {
 
 	gather: GatherMode,
 	server_opt: OptInOut,
+	consent_notice: Option<ConsentNotice>,
 	admin_control_mode: Control,
 	voice_control_mode: Control,
 	voicehunt_mode: Join,
 	label: Label,
 	custom_ack: Option<String>,
 	custom_prefix: Option<String>,
+	soundscape: Option<String>,
+	custom_sfx: Vec<CustomSfx>,
+	channel_filter: ChannelFilter,
+	population_rules: PopulationRules,
+	switch_rules: SwitchRules,
+	reaction_rules: ReactionRules,
+	duck_rules: DuckRules,
+	idle_rules: IdleRules,
+	playback: PlaybackPrefs,
+	schedule: Vec<ScheduleEntry>,
 	watchcat_domain: Option<ChannelId>,
 }
 
@@ -38,6 +57,8 @@ impl GuildState {
 
 		let server_opt = db.select_opt_in_out(guild).await.unwrap_or_default();
 
+		let consent_notice = db.select_consent_notice(guild).await.ok().flatten();
+
 		let admin_control_mode = db.select_control_admin_cfg(guild).await.unwrap_or_default();
 
 		let voice_control_mode = db.select_control_cfg(guild).await.unwrap_or_default();
@@ -48,6 +69,26 @@ impl GuildState {
 
 		let custom_prefix = db.select_prefix(guild).await.ok();
 
+		let soundscape = db.select_soundscape(guild).await.ok();
+
+		let custom_sfx = db.select_guild_sfx(guild).await.unwrap_or_default();
+
+		let channel_filter = db.select_channel_filter(guild).await.unwrap_or_default();
+
+		let population_rules = db.select_population_rules(guild).await.unwrap_or_default();
+
+		let switch_rules = db.select_switch_rules(guild).await.unwrap_or_default();
+
+		let reaction_rules = db.select_reaction_rules(guild).await.unwrap_or_default();
+
+		let duck_rules = db.select_duck_rules(guild).await.unwrap_or_default();
+
+		let idle_rules = db.select_idle_rules(guild).await.unwrap_or_default();
+
+		let playback = db.select_playback(guild).await.unwrap_or_default();
+
+		let schedule = db.select_schedule(guild).await.unwrap_or_default();
+
 		let voicehunt_mode = db.select_join_cfg(guild).await.unwrap_or_default();
 
 		let watchcat_domain = db.select_watchcat(guild).await.ok().map(ChannelId::new);
@@ -58,12 +99,23 @@ impl GuildState {
 
 			gather,
 			server_opt,
+			consent_notice,
 			admin_control_mode,
 			voice_control_mode,
 			voicehunt_mode,
 			label,
 			custom_ack,
 			custom_prefix,
+			soundscape,
+			custom_sfx,
+			channel_filter,
+			population_rules,
+			switch_rules,
+			reaction_rules,
+			duck_rules,
+			idle_rules,
+			playback,
+			schedule,
 			watchcat_domain,
 		}
 	}
@@ -79,6 +131,43 @@ impl GuildState {
 		builder.push_italic_line(format!("{:?}", self.voice_control_mode));
 		builder.push("Custom command prefix: ");
 		builder.push_italic_line_safe(format!("{:?}", self.custom_prefix));
+		builder.push("Soundscape: ");
+		builder.push_italic_line_safe(format!("{:?}", self.soundscape));
+		builder.push("Playback: ");
+		builder.push_italic_line(format!("{:?}", self.playback));
+
+		builder.push("Population rules: ");
+		builder.push_italic_line(format!("{:?}", self.population_rules));
+		builder.push("Channel switching: ");
+		builder.push_italic_line(format!("{:?}", self.switch_rules));
+		builder.push("Join/leave reactions: ");
+		builder.push_italic_line(format!("{:?}", self.reaction_rules));
+		builder.push("Ducking: ");
+		builder.push_italic_line(format!("{:?}", self.duck_rules));
+		builder.push("Idle timeout: ");
+		builder.push_italic_line(format!("{:?}", self.idle_rules));
+
+		if !self.channel_filter.is_empty() {
+			let list = |chans: &HashSet<ChannelId>| {
+				chans
+					.iter()
+					.map(|c| c.mention().to_string())
+					.collect::<Vec<_>>()
+					.join(", ")
+			};
+
+			builder.push("Channels allowed: ");
+			builder.push_line(list(&self.channel_filter.allow));
+			builder.push("Channels denied: ");
+			builder.push_line(list(&self.channel_filter.deny));
+		}
+
+		if !self.schedule.is_empty() {
+			builder.push_line("Schedule: ");
+			for entry in &self.schedule {
+				builder.push_italic_line(entry.to_string());
+			}
+		}
 
 		builder.push_bold_line(format!("Measurement details for {}:", self.guild));
 
@@ -97,6 +186,20 @@ impl GuildState {
 			} else {
 				builder.push_line("Server not being explicitly acknowledged.");
 			}
+
+			if let Some(notice) = &self.consent_notice {
+				builder.push("Consent notices posted in: ");
+				builder.push_line(notice.channel.mention().to_string());
+				builder.push("Consent notice template: ");
+				builder.push_italic_line_safe(
+					notice
+						.template
+						.as_deref()
+						.unwrap_or(ConsentNotice::DEFAULT_TEMPLATE),
+				);
+			} else {
+				builder.push_line("Consent notices not being posted.");
+			}
 		}
 
 		builder.build()
@@ -187,6 +290,17 @@ impl GuildState {
 		self.db.upsert_opt_in_out(self.guild, val).await;
 	}
 
+	pub fn consent_notice(&self) -> &Option<ConsentNotice> {
+		&self.consent_notice
+	}
+
+	pub async fn set_consent_notice(&mut self, val: Option<ConsentNotice>) {
+		self.db
+			.upsert_consent_notice(self.guild, self.server_opt, val.as_ref())
+			.await;
+		self.consent_notice = val;
+	}
+
 	pub fn admin_control_mode(&self) -> Control {
 		self.admin_control_mode
 	}
@@ -238,6 +352,143 @@ impl GuildState {
 		self.db.upsert_prefix(self.guild, &val).await;
 	}
 
+	pub fn soundscape(&self) -> &Option<String> {
+		&self.soundscape
+	}
+
+	pub async fn set_soundscape(&mut self, val: String) {
+		self.db.upsert_soundscape(self.guild, &val).await;
+		self.soundscape = Some(val);
+	}
+
+	pub fn custom_sfx(&self) -> &[CustomSfx] {
+		&self.custom_sfx
+	}
+
+	/// Adds (or replaces) an uploaded sound, returning any it replaced.
+	pub async fn add_custom_sfx(&mut self, val: CustomSfx) -> Option<CustomSfx> {
+		self.db.upsert_guild_sfx(self.guild, &val).await;
+
+		let old = self.custom_sfx.iter().position(|s| s.name == val.name);
+		let out = old.map(|i| self.custom_sfx.remove(i));
+
+		self.custom_sfx.push(val);
+		self.custom_sfx.sort_by(|a, b| a.name.cmp(&b.name));
+
+		out
+	}
+
+	pub async fn remove_custom_sfx(&mut self, name: &str) -> Option<CustomSfx> {
+		let pos = self.custom_sfx.iter().position(|s| s.name == name)?;
+
+		self.db.delete_guild_sfx(self.guild, name).await;
+
+		Some(self.custom_sfx.remove(pos))
+	}
+
+	pub fn channel_filter(&self) -> &ChannelFilter {
+		&self.channel_filter
+	}
+
+	/// Allows (`Some(true)`), denies (`Some(false)`), or forgets a channel
+	/// for autonomous hunting.
+	pub async fn set_channel_filter(&mut self, channel: ChannelId, allow: Option<bool>) {
+		match allow {
+			Some(allow) =>
+				self.db
+					.upsert_channel_filter(self.guild, channel, allow)
+					.await,
+			None => self.db.delete_channel_filter(self.guild, channel).await,
+		}
+
+		self.channel_filter.set(channel, allow);
+	}
+
+	pub fn population_rules(&self) -> PopulationRules {
+		self.population_rules
+	}
+
+	pub async fn set_population_rules(&mut self, rules: PopulationRules) {
+		self.db.upsert_population_rules(self.guild, rules).await;
+
+		self.population_rules = rules;
+	}
+
+	pub fn switch_rules(&self) -> SwitchRules {
+		self.switch_rules
+	}
+
+	pub async fn set_switch_rules(&mut self, rules: SwitchRules) {
+		let rules = rules.bounded();
+		self.db.upsert_switch_rules(self.guild, rules).await;
+
+		self.switch_rules = rules;
+	}
+
+	pub fn reaction_rules(&self) -> ReactionRules {
+		self.reaction_rules
+	}
+
+	pub async fn set_reaction_rules(&mut self, rules: ReactionRules) {
+		self.db.upsert_reaction_rules(self.guild, rules).await;
+
+		self.reaction_rules = rules;
+	}
+
+	pub fn duck_rules(&self) -> DuckRules {
+		self.duck_rules
+	}
+
+	pub async fn set_duck_rules(&mut self, rules: DuckRules) {
+		self.db.upsert_duck_rules(self.guild, rules).await;
+
+		self.duck_rules = rules;
+	}
+
+	pub fn idle_rules(&self) -> IdleRules {
+		self.idle_rules
+	}
+
+	pub async fn set_idle_rules(&mut self, rules: IdleRules) {
+		self.db.upsert_idle_rules(self.guild, rules).await;
+
+		self.idle_rules = rules;
+	}
+
+	pub fn playback(&self) -> PlaybackPrefs {
+		self.playback
+	}
+
+	pub async fn set_playback(&mut self, prefs: PlaybackPrefs) {
+		self.db.upsert_playback(self.guild, prefs).await;
+
+		self.playback = prefs;
+	}
+
+	pub fn schedule(&self) -> &[ScheduleEntry] {
+		&self.schedule
+	}
+
+	/// Adds an entry to the server's schedule, numbering it after the last.
+	pub async fn add_schedule_entry(&mut self, mut entry: ScheduleEntry) -> u32 {
+		entry.id = self.schedule.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+
+		self.db.upsert_schedule_entry(self.guild, &entry).await;
+
+		let id = entry.id;
+		self.schedule.push(entry);
+
+		id
+	}
+
+	pub async fn remove_schedule_entry(&mut self, id: u32) -> Option<ScheduleEntry> {
+		let pos = self.schedule.iter().position(|e| e.id == id)?;
+
+		self.db.delete_schedule_entry(self.guild, id).await;
+
+		Some(self.schedule.remove(pos))
+	}
+
 	pub fn watchcat_domain(&self) -> &Option<ChannelId> {
 		&self.watchcat_domain
 	}
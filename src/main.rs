@@ -3,10 +3,14 @@ mod automata;
 mod commands;
 mod config;
 mod constants;
+mod custom_sfx;
 mod dbs;
 mod event_handler;
 mod guild;
+mod schedule;
 mod server;
+mod soundscape;
+mod trace_sink;
 mod user;
 mod voicehunt;
 mod watchcat;
@@ -14,8 +18,12 @@ mod watchcat;
 use crate::{
 	audio_resources::*,
 	config::BotConfig,
+	constants::*,
+	custom_sfx::CustomSfxSettings,
 	dbs::*,
 	guild::GuildStates,
+	soundscape::{SoundscapeKey, Soundscapes},
+	trace_sink::{retention::spawn_retention_task, TraceSinkKey, TraceStore},
 	user::*,
 	voicehunt::*,
 	watchcat::*,
@@ -28,8 +36,8 @@ use serenity::{
 	prelude::*,
 };
 use songbird::{self, SerenityInit};
-use std::{collections::HashSet, env, sync::Arc};
-use tokio::{fs::File, io::AsyncReadExt};
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
+use tokio::{fs::File, io::AsyncReadExt, time};
 
 use tracing::*;
 
@@ -49,6 +57,23 @@ fn help() {
 	println!("Mrow mia mrowr?! (Myaster! One file! One token?!)");
 }
 
+async fn shutdown_signal() {
+	#[cfg(unix)]
+	{
+		use tokio::signal::unix::{signal, SignalKind};
+
+		let mut term = signal(SignalKind::terminate()).expect("Couldn't listen for SIGTERM.");
+
+		tokio::select! {
+			_ = tokio::signal::ctrl_c() => {},
+			_ = term.recv() => {},
+		}
+	}
+
+	#[cfg(not(unix))]
+	let _ = tokio::signal::ctrl_c().await;
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() {
 	tracing_subscriber::fmt::init();
@@ -86,6 +111,17 @@ async fn main() {
 		},
 	};
 
+	let soundscapes = match Soundscapes::load_dir(&bot_config.soundscape_dir).await {
+		Ok(s) => Arc::new(s),
+		Err(e) => {
+			error!(
+				"Mrowr?! (Couldn't load soundscapes from '{}': {})",
+				bot_config.soundscape_dir, e
+			);
+			return;
+		},
+	};
+
 	let (owners, bot_id) = {
 		let http = Http::new(&token_raw);
 
@@ -141,6 +177,7 @@ async fn main() {
 		.group(&commands::EVERYONE_GROUP)
 		.group(&commands::CONTROL_GROUP)
 		.group(&commands::ADMIN_GROUP)
+		.group(&commands::OWNER_GROUP)
 		.help(&commands::MY_HELP);
 
 	framework.configure(config);
@@ -171,13 +208,46 @@ async fn main() {
 		data.insert::<GuildStates>(Default::default());
 		data.insert::<UserStateKey>(Arc::new(UserState::new(db.clone()).await));
 
+		let trace_store = Arc::new(TraceStore::new(&bot_config.traces, db.clone()));
+		spawn_retention_task(trace_store.clone());
+		data.insert::<TraceSinkKey>(trace_store);
+
 		data.insert::<Db>(db);
 		data.insert::<Owners>(owners);
 		data.insert::<MyId>(bot_id);
 
-		data.insert::<Resources>(preload_resources().await);
+		data.insert::<Resources>(preload_resources(&soundscapes).await);
+		data.insert::<SoundscapeKey>(soundscapes);
+		data.insert::<CustomSfxSettings>(Arc::new(bot_config.custom_sfx.clone()));
 	}
 
+	// Send everyone home and save any traces before going offline.
+	let data = client.data.clone();
+	let shard_manager = client.shard_manager.clone();
+	tokio::spawn(async move {
+		shutdown_signal().await;
+		info!("Mrrrow... (Shutting down: carting from all calls.)");
+
+		voicehunt_shutdown(&data, Duration::from_secs(SHUTDOWN_OUTRO_TIMEOUT)).await;
+
+		let store = {
+			let datas = data.read().await;
+			datas.get::<TraceSinkKey>().cloned()
+		};
+
+		if let Some(store) = store {
+			let flush = store.flush();
+			if time::timeout(Duration::from_secs(SHUTDOWN_TRACE_TIMEOUT), flush)
+				.await
+				.is_err()
+			{
+				warn!("Nya... (Gave up waiting on trace writes during shutdown.)");
+			}
+		}
+
+		shard_manager.shutdown_all().await;
+	});
+
 	// Now, log in.
 	client
 		.start_shards(2)
@@ -0,0 +1,314 @@
+use crate::{
+	constants::SCHEDULE_TICK_SECS,
+	guild::GuildStates,
+	voicehunt::{voicehunt_control, VoiceHuntCommand},
+};
+use chrono::{DateTime, Datelike, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use enum_primitive::*;
+use serenity::{client::Context, model::prelude::GuildId};
+use std::{
+	collections::HashMap,
+	fmt,
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
+};
+use tokio::time;
+use tokio_postgres::Row;
+use tracing::info;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+enum_from_primitive! {
+/// What Felyne does during a scheduled window.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScheduleKind {
+	Hunt = 0,
+	Watch,
+	Quiet,
+}
+}
+
+const SKINDS: &[&str] = &["hunt", "watch", "quiet"];
+
+impl ScheduleKind {
+	pub fn from_str(label: &str) -> Option<Self> {
+		SKINDS
+			.iter()
+			.position(|k| *k == label)
+			.and_then(Self::from_usize)
+	}
+
+	pub fn label(self) -> &'static str {
+		SKINDS[self as usize]
+	}
+
+	/// Quiet hours win over any join window they overlap, and hunting over watching.
+	fn precedence(self) -> u8 {
+		match self {
+			Self::Watch => 0,
+			Self::Hunt => 1,
+			Self::Quiet => 2,
+		}
+	}
+
+	pub fn as_command(self) -> VoiceHuntCommand {
+		match self {
+			Self::Hunt => VoiceHuntCommand::BraveHunt,
+			Self::Watch => VoiceHuntCommand::Stalk,
+			Self::Quiet => VoiceHuntCommand::Carted,
+		}
+	}
+}
+
+/// Days of the week a window starts on, Monday in the lowest bit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Days(u8);
+
+impl Days {
+	pub const ALL: Self = Self(0b111_1111);
+
+	/// Reads e.g. `daily`, `weekdays`, `weekends`, or `mon,wed,fri`.
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"daily" | "every" | "all" => Some(Self::ALL),
+			"weekdays" => Some(Self(0b001_1111)),
+			"weekends" => Some(Self(0b110_0000)),
+			list => list
+				.split(',')
+				.map(|day| day.parse::<Weekday>().ok())
+				.try_fold(Self(0), |days, day| {
+					day.map(|d| Self(days.0 | 1 << d.num_days_from_monday()))
+				}),
+		}
+	}
+
+	pub fn bits(self) -> i32 {
+		i32::from(self.0)
+	}
+
+	pub fn contains(self, day: Weekday) -> bool {
+		self.0 & (1 << day.num_days_from_monday()) != 0
+	}
+}
+
+impl fmt::Display for Days {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.0 {
+			0b111_1111 => write!(f, "daily"),
+			0b001_1111 => write!(f, "weekdays"),
+			0b110_0000 => write!(f, "weekends"),
+			_ => {
+				let days: Vec<String> =
+					std::iter::successors(Some(Weekday::Mon), |d| Some(d.succ()))
+						.take(7)
+						.filter(|d| self.contains(*d))
+						.map(|d| d.to_string().to_lowercase())
+						.collect();
+				write!(f, "{}", days.join(","))
+			},
+		}
+	}
+}
+
+/// A window of local time, on certain days, when Felyne should hunt, watch,
+/// or keep quiet. Windows may run past midnight.
+#[derive(Clone, Debug)]
+pub struct ScheduleEntry {
+	pub id: u32,
+	pub kind: ScheduleKind,
+	pub days: Days,
+	pub start: NaiveTime,
+	pub end: NaiveTime,
+	pub tz: Tz,
+}
+
+impl ScheduleEntry {
+	pub fn from_row(row: &Row) -> Option<Self> {
+		let id: i32 = row.get(0);
+		let days: i32 = row.get(2);
+		let start: i32 = row.get(3);
+		let end: i32 = row.get(4);
+		let tz: String = row.get(5);
+
+		let minute = |m: i32| NaiveTime::from_hms_opt(m as u32 / 60, m as u32 % 60, 0);
+
+		Some(Self {
+			id: id as u32,
+			kind: ScheduleKind::from_i32(row.get(1))?,
+			days: Days(days as u8 & Days::ALL.0),
+			start: minute(start)?,
+			end: minute(end)?,
+			tz: tz.parse().unwrap_or(Tz::UTC),
+		})
+	}
+
+	/// Reads a window such as `20:00-23:30`.
+	pub fn parse_window(text: &str) -> Option<(NaiveTime, NaiveTime)> {
+		let (start, end) = text.split_once('-')?;
+		let start = NaiveTime::parse_from_str(start, "%H:%M").ok()?;
+		let end = NaiveTime::parse_from_str(end, "%H:%M").ok()?;
+
+		Some((start, end)).filter(|_| start != end)
+	}
+
+	pub fn start_minute(&self) -> i32 {
+		minute_of_day(self.start) as i32
+	}
+
+	pub fn end_minute(&self) -> i32 {
+		minute_of_day(self.end) as i32
+	}
+
+	pub fn active_at(&self, now: DateTime<Utc>) -> bool {
+		let local = now.with_timezone(&self.tz);
+		let (today, time) = (local.weekday(), minute_of_day(local.time()));
+		let (start, end) = (minute_of_day(self.start), minute_of_day(self.end));
+
+		if start < end {
+			self.days.contains(today) && (start..end).contains(&time)
+		} else {
+			(self.days.contains(today) && time >= start)
+				|| (self.days.contains(today.pred()) && time < end)
+		}
+	}
+}
+
+impl fmt::Display for ScheduleEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"#{}: {} {}, {}-{} ({})",
+			self.id,
+			self.kind.label(),
+			self.days,
+			self.start.format("%H:%M"),
+			self.end.format("%H:%M"),
+			self.tz.name(),
+		)
+	}
+}
+
+fn minute_of_day(time: NaiveTime) -> u32 {
+	time.hour() * 60 + time.minute()
+}
+
+/// What a server's schedule says Felyne should be doing at `now`, if anything.
+pub fn scheduled(entries: &[ScheduleEntry], now: DateTime<Utc>) -> Option<ScheduleKind> {
+	entries
+		.iter()
+		.filter(|e| e.active_at(now))
+		.map(|e| e.kind)
+		.max_by_key(|k| k.precedence())
+}
+
+/// Checks every server's schedule once per `SCHEDULE_TICK_SECS`, moving Felyne
+/// whenever a window opens or closes. Once a window closes, she goes back to
+/// whatever the server last asked of her, so commands used mid-window only
+/// last until the next boundary.
+pub fn spawn_schedule_task(ctx: Context) {
+	if STARTED.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	tokio::spawn(async move {
+		let mut applied: HashMap<GuildId, ScheduleKind> = HashMap::new();
+		let mut interval = time::interval(Duration::from_secs(SCHEDULE_TICK_SECS));
+
+		loop {
+			interval.tick().await;
+
+			let states: Vec<_> = {
+				let datas = ctx.data.read().await;
+				datas
+					.get::<GuildStates>()
+					.map(|gs| gs.iter().map(|e| (*e.key(), e.value().clone())).collect())
+					.unwrap_or_default()
+			};
+
+			let now = Utc::now();
+
+			for (guild_id, state) in states {
+				let (want, join) = {
+					let lock = state.read().await;
+					(scheduled(lock.schedule(), now), lock.join())
+				};
+
+				let had = match want {
+					Some(kind) => applied.insert(guild_id, kind),
+					None => applied.remove(&guild_id),
+				};
+
+				if had == want {
+					continue;
+				}
+
+				info!(
+					"[Schedule] {:?} moving from {:?} to {:?}.",
+					guild_id, had, want
+				);
+
+				let cmd = want
+					.map(ScheduleKind::as_command)
+					.unwrap_or_else(|| join.as_command());
+				voicehunt_control(&ctx, guild_id, cmd).await;
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use chrono::TimeZone;
+
+	fn entry(kind: ScheduleKind, days: &str, window: &str, tz: Tz) -> ScheduleEntry {
+		let (start, end) = ScheduleEntry::parse_window(window).unwrap();
+
+		ScheduleEntry {
+			id: 1,
+			kind,
+			days: Days::parse(days).unwrap(),
+			start,
+			end,
+			tz,
+		}
+	}
+
+	#[test]
+	fn test_days_parse() {
+		assert_eq!(Days::parse("daily"), Some(Days::ALL));
+		assert_eq!(Days::parse("mon,wed").unwrap().to_string(), "mon,wed");
+		assert!(Days::parse("weekends").unwrap().contains(Weekday::Sun));
+		assert_eq!(Days::parse("caturday"), None);
+	}
+
+	#[test]
+	fn test_window_in_local_time() {
+		// 2024-01-05 is a Friday.
+		let raid = entry(
+			ScheduleKind::Hunt,
+			"fri",
+			"20:00-23:00",
+			chrono_tz::Europe::Berlin,
+		);
+
+		assert!(raid.active_at(Utc.with_ymd_and_hms(2024, 1, 5, 19, 30, 0).unwrap()));
+		assert!(!raid.active_at(Utc.with_ymd_and_hms(2024, 1, 5, 22, 30, 0).unwrap()));
+		assert!(!raid.active_at(Utc.with_ymd_and_hms(2024, 1, 6, 19, 30, 0).unwrap()));
+	}
+
+	#[test]
+	fn test_overnight_quiet_wins() {
+		let quiet = entry(ScheduleKind::Quiet, "fri", "23:00-07:00", Tz::UTC);
+		let hunt = entry(ScheduleKind::Hunt, "daily", "06:00-12:00", Tz::UTC);
+		let entries = [quiet, hunt];
+
+		let sat = |h| Utc.with_ymd_and_hms(2024, 1, 6, h, 0, 0).unwrap();
+
+		assert_eq!(scheduled(&entries, sat(1)), Some(ScheduleKind::Quiet));
+		assert_eq!(scheduled(&entries, sat(6)), Some(ScheduleKind::Quiet));
+		assert_eq!(scheduled(&entries, sat(7)), Some(ScheduleKind::Hunt));
+		assert_eq!(scheduled(&entries, sat(13)), None);
+	}
+}
@@ -0,0 +1,748 @@
+use crate::{automata::*, constants::DEFAULT_SOUNDSCAPE};
+use rand::{distributions::*, thread_rng};
+use serde::Deserialize;
+use serenity::prelude::TypeMapKey;
+use std::{
+	collections::HashMap,
+	fmt,
+	hash::Hash,
+	io::Error as IoError,
+	path::Path,
+	sync::Arc,
+	time::Duration,
+};
+use tokio::fs;
+
+pub struct SoundscapeKey;
+
+impl TypeMapKey for SoundscapeKey {
+	type Value = Arc<Soundscapes>;
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BgmInput {
+	TryIntro,
+	Advance,
+	MoveOutro,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SfxInput {
+	Advance,
+}
+
+/// Which of a server's volume multipliers a state's tracks are scaled by.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SoundClass {
+	Bgm,
+	Ambience,
+	Sfx,
+}
+
+/// Someone arriving in or leaving Felyne's channel, which she can react to with a sound.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Movement {
+	Join,
+	Leave,
+}
+
+/// Index of a state within its (compiled) machine.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct StateId(usize);
+
+/// Soundscape file contents, as written by hand.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SoundscapeDef {
+	pub bgm: MachineDef<BgmInput>,
+	pub sfx: MachineDef<SfxInput>,
+	/// SFX state to play quietly when joining in stealth, so that Discord
+	/// starts sending us voice packets.
+	#[serde(default)]
+	pub stealth_ping: Option<StealthPingDef>,
+	/// Overlap between consecutive BGM tracks, fading one out as the next fades in.
+	#[serde(default)]
+	pub crossfade_ms: u64,
+	/// SFX states to play from when someone joins or leaves Felyne's channel.
+	#[serde(default)]
+	pub reactions: HashMap<Movement, String>,
+	/// BGM states to play from when a `!quest` starts, is cleared, or runs out of time.
+	#[serde(default)]
+	pub quest: Option<QuestCuesDef>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MachineDef<Input> {
+	/// Directory (relative to the working directory) holding this machine's tracks.
+	pub folder: String,
+	pub start: String,
+	pub states: HashMap<String, StateDef>,
+	#[serde(default = "Vec::new")]
+	pub transitions: Vec<TransitionDef<Input>>,
+	/// Loop points for any tracks which should repeat a section before ending,
+	/// keyed by file name.
+	#[serde(default)]
+	pub loops: HashMap<String, LoopDef>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateDef {
+	#[serde(default)]
+	pub tracks: Vec<String>,
+	#[serde(default = "StateDef::default_volume")]
+	pub volume: [f32; 2],
+	/// Prevent any SFX from starting while in this state.
+	#[serde(default)]
+	pub blocks_sfx: bool,
+	/// Leave the call once this state's track has finished.
+	#[serde(default)]
+	pub terminal: bool,
+	/// Mix sounds uploaded by a server into this state's tracks.
+	#[serde(default)]
+	pub custom: bool,
+	/// Defaults to `bgm` or `sfx`, according to the machine.
+	#[serde(default)]
+	pub class: Option<SoundClass>,
+	/// SFX transitions to cool down upon entering this (BGM) state.
+	#[serde(default)]
+	pub hush: Vec<TransitionDef<SfxInput>>,
+}
+
+impl StateDef {
+	fn default_volume() -> [f32; 2] {
+		[1.0, 1.0]
+	}
+}
+
+/// A transition between two states. A `from` of `"*"` adds the transition
+/// to every state other than `to`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TransitionDef<Input> {
+	pub from: String,
+	pub to: String,
+	pub on: Input,
+	#[serde(default)]
+	pub priority: usize,
+	#[serde(default)]
+	pub cooldown: Option<CooldownDef>,
+}
+
+/// A fixed cooldown of `min_ms`, or uniformly drawn up to `max_ms`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CooldownDef {
+	pub min_ms: u64,
+	#[serde(default)]
+	pub max_ms: Option<u64>,
+	#[serde(default)]
+	pub refresh: bool,
+	#[serde(default)]
+	pub start_used: bool,
+}
+
+impl CooldownDef {
+	fn compile(&self) -> Result<Cooldown, SoundscapeError> {
+		let min = Duration::from_millis(self.min_ms);
+
+		let duration: DurationSource = match self.max_ms {
+			Some(max) if max > self.min_ms =>
+				Uniform::new(min, Duration::from_millis(max)).into(),
+			Some(max) if max < self.min_ms =>
+				return Err(SoundscapeError::BadCooldown(self.min_ms, max)),
+			_ => min.into(),
+		};
+
+		Ok(Cooldown::new(duration, self.refresh, self.start_used))
+	}
+}
+
+/// Jump back to `start_ms` upon reaching `end_ms`, `times` times over.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LoopDef {
+	pub start_ms: u64,
+	pub end_ms: u64,
+	#[serde(default = "LoopDef::default_times")]
+	pub times: usize,
+}
+
+impl LoopDef {
+	fn default_times() -> usize {
+		1
+	}
+
+	fn compile(&self, file: &str) -> Result<LoopPoints, SoundscapeError> {
+		if self.end_ms <= self.start_ms {
+			return Err(SoundscapeError::BadLoop(file.to_string()));
+		}
+
+		Ok(LoopPoints {
+			start: Duration::from_millis(self.start_ms),
+			end: Duration::from_millis(self.end_ms),
+			times: self.times,
+		})
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuestCuesDef {
+	pub start: String,
+	pub clear: String,
+	pub timeout: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StealthPingDef {
+	pub state: String,
+	pub volume: f32,
+}
+
+#[derive(Debug)]
+pub enum SoundscapeError {
+	Io(IoError),
+	Parse(serde_json::Error),
+	UnknownState(&'static str, String),
+	BadVolume(String),
+	BadCooldown(u64, u64),
+	BadLoop(String),
+	InPack(String, Box<SoundscapeError>),
+	MissingDefault,
+}
+
+impl fmt::Display for SoundscapeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "couldn't read pack: {}", e),
+			Self::Parse(e) => write!(f, "couldn't parse pack: {}", e),
+			Self::UnknownState(kind, name) => write!(f, "no {} state named '{}'", kind, name),
+			Self::BadVolume(state) => write!(
+				f,
+				"state '{}' needs volumes in 0..=1, with min no more than max",
+				state
+			),
+			Self::BadCooldown(min, max) => write!(
+				f,
+				"cooldown max ({}ms) is shorter than its min ({}ms)",
+				max, min
+			),
+			Self::BadLoop(file) => write!(f, "loop in '{}' must end after it starts", file),
+			Self::InPack(pack, e) => write!(f, "in pack '{}': {}", pack, e),
+			Self::MissingDefault => write!(f, "no '{}' pack", DEFAULT_SOUNDSCAPE),
+		}
+	}
+}
+
+impl From<IoError> for SoundscapeError {
+	fn from(e: IoError) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<serde_json::Error> for SoundscapeError {
+	fn from(e: serde_json::Error) -> Self {
+		Self::Parse(e)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LoopPoints {
+	pub start: Duration,
+	pub end: Duration,
+	pub times: usize,
+}
+
+impl LoopPoints {
+	/// How much longer looping makes a track.
+	pub fn extra(&self) -> Duration {
+		(self.end - self.start) * self.times as u32
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VolumeRange {
+	min: f32,
+	max: f32,
+}
+
+impl Default for VolumeRange {
+	fn default() -> Self {
+		Self { min: 1.0, max: 1.0 }
+	}
+}
+
+impl SoundState {
+	/// A random track from this state, if it has any.
+	pub fn draw_track(&self) -> Option<&str> {
+		if self.tracks.is_empty() {
+			None
+		} else {
+			let idx = Uniform::new(0, self.tracks.len()).sample(&mut thread_rng());
+			Some(&self.tracks[idx])
+		}
+	}
+}
+
+impl VolumeRange {
+	pub fn draw(&self) -> f32 {
+		if self.max > self.min {
+			Uniform::new(self.min, self.max).sample(&mut thread_rng())
+		} else {
+			self.min
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct Hush {
+	from: StateId,
+	to: StateId,
+	on: SfxInput,
+	priority: usize,
+	cooldown: Option<Cooldown>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SoundState {
+	pub name: String,
+	/// Keys into the loaded `RxMap`.
+	pub tracks: Vec<String>,
+	pub volume: VolumeRange,
+	pub blocks_sfx: bool,
+	pub terminal: bool,
+	pub custom: bool,
+	pub class: SoundClass,
+	hush: Vec<Hush>,
+}
+
+#[derive(Debug)]
+pub struct Machine<Input: Hash + Eq + Copy> {
+	states: Vec<SoundState>,
+	loops: HashMap<String, LoopPoints>,
+	template: TimedMachine<StateId, Input>,
+}
+
+impl<Input: Hash + Eq + Copy> Machine<Input> {
+	fn compile(kind: &'static str, def: &MachineDef<Input>) -> Result<Self, SoundscapeError> {
+		let mut names: Vec<&String> = def.states.keys().collect();
+		names.sort();
+
+		let ids: HashMap<&str, StateId> = names
+			.iter()
+			.enumerate()
+			.map(|(i, name)| (name.as_str(), StateId(i)))
+			.collect();
+		let lookup = |name: &str| {
+			ids.get(name)
+				.copied()
+				.ok_or_else(|| SoundscapeError::UnknownState(kind, name.to_string()))
+		};
+
+		let default_class = if kind == "sfx" {
+			SoundClass::Sfx
+		} else {
+			SoundClass::Bgm
+		};
+
+		let mut states = Vec::with_capacity(names.len());
+		for name in &names {
+			let state = &def.states[*name];
+			let [min, max] = state.volume;
+
+			if !(0.0..=1.0).contains(&min) || !(min..=1.0).contains(&max) {
+				return Err(SoundscapeError::BadVolume(name.to_string()));
+			}
+
+			states.push(SoundState {
+				name: name.to_string(),
+				tracks: state
+					.tracks
+					.iter()
+					.map(|file| format!("{}/{}", def.folder, file))
+					.collect(),
+				volume: VolumeRange { min, max },
+				blocks_sfx: state.blocks_sfx,
+				terminal: state.terminal,
+				custom: state.custom,
+				class: state.class.unwrap_or(default_class),
+				hush: vec![],
+			});
+		}
+
+		let mut template = TimedMachine::new(lookup(&def.start)?);
+		for i in 0..states.len() {
+			template.register_state(StateId(i));
+		}
+
+		for tx in &def.transitions {
+			let to = lookup(&tx.to)?;
+			let cooldown = tx.cooldown.as_ref().map(CooldownDef::compile).transpose()?;
+
+			let froms = if tx.from == "*" {
+				(0..states.len())
+					.map(StateId)
+					.filter(|s| *s != to)
+					.collect()
+			} else {
+				vec![lookup(&tx.from)?]
+			};
+
+			for from in froms {
+				template.add_priority_transition(from, to, tx.on, tx.priority, cooldown.clone());
+			}
+		}
+
+		let loops = def
+			.loops
+			.iter()
+			.map(|(file, points)| Ok((format!("{}/{}", def.folder, file), points.compile(file)?)))
+			.collect::<Result<_, SoundscapeError>>()?;
+
+		Ok(Self {
+			states,
+			loops,
+			template,
+		})
+	}
+
+	/// A fresh copy of this machine, ready to hunt with.
+	pub fn build(&self) -> TimedMachine<StateId, Input> {
+		self.template.clone()
+	}
+
+	pub fn state(&self, id: StateId) -> &SoundState {
+		&self.states[id.0]
+	}
+
+	pub fn find(&self, name: &str) -> Option<StateId> {
+		self.states.iter().position(|s| s.name == name).map(StateId)
+	}
+
+	pub fn tracks(&self) -> impl Iterator<Item = &str> {
+		self.states
+			.iter()
+			.flat_map(|s| s.tracks.iter().map(String::as_str))
+	}
+
+	/// Finds a track by its file name, minus folder and extension (e.g., `5815`),
+	/// alongside the volume of the state which plays it.
+	pub fn find_track(&self, name: &str) -> Option<(&str, VolumeRange)> {
+		self.states.iter().find_map(|s| {
+			s.tracks
+				.iter()
+				.find(|t| track_stem(t).eq_ignore_ascii_case(name))
+				.map(|t| (t.as_str(), s.volume))
+		})
+	}
+
+	/// The class of whichever state plays `track`.
+	pub fn track_class(&self, track: &str) -> Option<SoundClass> {
+		self.states
+			.iter()
+			.find(|s| s.tracks.iter().any(|t| t == track))
+			.map(|s| s.class)
+	}
+
+	pub fn loop_points(&self, track: &str) -> Option<&LoopPoints> {
+		self.loops.get(track)
+	}
+
+	/// Volume for sounds uploaded by a server, if any state mixes them in.
+	pub fn custom_volume(&self) -> Option<VolumeRange> {
+		self.states.iter().find(|s| s.custom).map(|s| s.volume)
+	}
+}
+
+pub fn track_stem(track: &str) -> &str {
+	Path::new(track)
+		.file_stem()
+		.and_then(|s| s.to_str())
+		.unwrap_or(track)
+}
+
+#[derive(Debug)]
+pub struct Soundscape {
+	pub bgm: Machine<BgmInput>,
+	pub sfx: Machine<SfxInput>,
+	pub stealth_ping: Option<(StateId, f32)>,
+	pub crossfade: Option<Duration>,
+	pub reactions: HashMap<Movement, StateId>,
+	pub quest: Option<QuestCues>,
+}
+
+/// BGM states whose tracks mark the stages of a quest, played over whatever's going.
+#[derive(Clone, Copy, Debug)]
+pub struct QuestCues {
+	pub start: StateId,
+	pub clear: StateId,
+	pub timeout: StateId,
+}
+
+impl Soundscape {
+	pub async fn load(path: impl AsRef<Path>) -> Result<Self, SoundscapeError> {
+		let text = fs::read_to_string(path).await?;
+
+		Self::from_json(&text)
+	}
+
+	pub fn from_json(text: &str) -> Result<Self, SoundscapeError> {
+		Self::compile(&serde_json::from_str(text)?)
+	}
+
+	pub fn compile(def: &SoundscapeDef) -> Result<Self, SoundscapeError> {
+		let sfx = Machine::compile("sfx", &def.sfx)?;
+		let mut bgm = Machine::compile("bgm", &def.bgm)?;
+
+		let sfx_lookup = |name: &str| {
+			sfx.find(name)
+				.ok_or_else(|| SoundscapeError::UnknownState("sfx", name.to_string()))
+		};
+
+		for state in bgm.states.iter_mut() {
+			for tx in &def.bgm.states[&state.name].hush {
+				state.hush.push(Hush {
+					from: sfx_lookup(&tx.from)?,
+					to: sfx_lookup(&tx.to)?,
+					on: tx.on,
+					priority: tx.priority,
+					cooldown: tx.cooldown.as_ref().map(CooldownDef::compile).transpose()?,
+				});
+			}
+		}
+
+		let stealth_ping = def
+			.stealth_ping
+			.as_ref()
+			.map(|ping| sfx_lookup(&ping.state).map(|id| (id, ping.volume)))
+			.transpose()?;
+
+		let reactions = def
+			.reactions
+			.iter()
+			.map(|(movement, state)| sfx_lookup(state).map(|id| (*movement, id)))
+			.collect::<Result<_, _>>()?;
+
+		let bgm_lookup = |name: &str| {
+			bgm.find(name)
+				.ok_or_else(|| SoundscapeError::UnknownState("bgm", name.to_string()))
+		};
+
+		let quest = def
+			.quest
+			.as_ref()
+			.map(|cues| -> Result<_, SoundscapeError> {
+				Ok(QuestCues {
+					start: bgm_lookup(&cues.start)?,
+					clear: bgm_lookup(&cues.clear)?,
+					timeout: bgm_lookup(&cues.timeout)?,
+				})
+			})
+			.transpose()?;
+
+		Ok(Self {
+			bgm,
+			sfx,
+			stealth_ping,
+			crossfade: Some(Duration::from_millis(def.crossfade_ms)).filter(|d| !d.is_zero()),
+			reactions,
+			quest,
+		})
+	}
+
+	/// Applies any SFX cooldowns caused by entering a BGM state.
+	pub fn hush_sfx(&self, bgm_state: StateId, sfx: &mut TimedMachine<StateId, SfxInput>) {
+		for hush in &self.bgm.state(bgm_state).hush {
+			sfx.cause_cooldown(
+				hush.from,
+				hush.to,
+				hush.on,
+				hush.priority,
+				hush.cooldown.clone(),
+			);
+		}
+	}
+
+	/// Every track any state might play.
+	pub fn tracks(&self) -> impl Iterator<Item = &str> {
+		self.bgm.tracks().chain(self.sfx.tracks())
+	}
+}
+
+/// Every soundscape pack guilds can choose from, keyed by file name.
+#[derive(Debug)]
+pub struct Soundscapes {
+	packs: HashMap<String, Arc<Soundscape>>,
+}
+
+impl Soundscapes {
+	/// Loads each `<name>.json` in `dir` as a pack. One must be named `default`.
+	pub async fn load_dir(dir: impl AsRef<Path>) -> Result<Self, SoundscapeError> {
+		let mut packs = HashMap::new();
+		let mut entries = fs::read_dir(dir).await?;
+
+		while let Some(entry) = entries.next_entry().await? {
+			let path = entry.path();
+
+			if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+				continue;
+			}
+
+			if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+				let pack = Soundscape::load(&path)
+					.await
+					.map_err(|e| SoundscapeError::InPack(name.to_string(), Box::new(e)))?;
+
+				packs.insert(name.to_string(), Arc::new(pack));
+			}
+		}
+
+		if !packs.contains_key(DEFAULT_SOUNDSCAPE) {
+			return Err(SoundscapeError::MissingDefault);
+		}
+
+		Ok(Self { packs })
+	}
+
+	/// The named pack, or the default if it doesn't exist (anymore).
+	pub fn get(&self, name: Option<&str>) -> Arc<Soundscape> {
+		name.and_then(|n| self.packs.get(n))
+			.unwrap_or_else(|| &self.packs[DEFAULT_SOUNDSCAPE])
+			.clone()
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.packs.contains_key(name)
+	}
+
+	pub fn names(&self) -> Vec<&str> {
+		let mut names: Vec<&str> = self.packs.keys().map(String::as_str).collect();
+		names.sort_unstable();
+		names
+	}
+
+	/// Every track any pack might play.
+	pub fn tracks(&self) -> impl Iterator<Item = &str> {
+		self.packs.values().flat_map(|pack| pack.tracks())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const DEFAULT: &str = include_str!("../soundscapes/default.json");
+
+	#[test]
+	fn test_default_soundscape_compiles() {
+		let scape = Soundscape::from_json(DEFAULT).unwrap();
+
+		assert!(scape.stealth_ping.is_some());
+		assert!(scape.tracks().any(|t| t == "bgm/5799.opus"));
+	}
+
+	#[test]
+	fn test_default_soundscape_reactions() {
+		let scape = Soundscape::from_json(DEFAULT).unwrap();
+
+		for movement in &[Movement::Join, Movement::Leave] {
+			let state = scape.sfx.state(scape.reactions[movement]);
+			assert!(state.draw_track().is_some());
+		}
+	}
+
+	#[test]
+	fn test_default_soundscape_quest_cues() {
+		let scape = Soundscape::from_json(DEFAULT).unwrap();
+		let cues = scape.quest.unwrap();
+
+		for id in &[cues.start, cues.clear, cues.timeout] {
+			assert!(scape.bgm.state(*id).draw_track().is_some());
+		}
+	}
+
+	#[test]
+	fn test_default_soundscape_outro_from_anywhere() {
+		let scape = Soundscape::from_json(DEFAULT).unwrap();
+		let mut bgm = scape.bgm.build();
+
+		bgm.advance(BgmInput::Advance);
+		let state = bgm.advance(BgmInput::MoveOutro).unwrap();
+
+		assert!(scape.bgm.state(state).terminal);
+	}
+
+	#[tokio::test]
+	async fn test_pack_dir_falls_back_to_default() {
+		let packs = Soundscapes::load_dir("soundscapes/").await.unwrap();
+
+		assert!(packs.contains(DEFAULT_SOUNDSCAPE));
+		assert!(Arc::ptr_eq(
+			&packs.get(Some("no-such-pack")),
+			&packs.get(None)
+		));
+	}
+
+	#[test]
+	fn test_find_track_by_stem() {
+		let scape = Soundscape::from_json(DEFAULT).unwrap();
+
+		let (file, _) = scape.bgm.find_track("5815").unwrap();
+
+		assert_eq!(file, "bgm/5815.opus");
+		assert_eq!(scape.bgm.track_class(file), Some(SoundClass::Bgm));
+		assert_eq!(
+			scape.bgm.track_class("bgm/5824.opus"),
+			Some(SoundClass::Ambience)
+		);
+		assert!(scape.sfx.find_track("5815").is_none());
+		assert!(scape.sfx.custom_volume().is_some());
+	}
+
+	#[test]
+	fn test_loop_points_and_crossfade() {
+		let text = r#"{
+			"bgm": {
+				"folder": "bgm",
+				"start": "none",
+				"states": { "none": { "tracks": ["a.opus"] } },
+				"loops": { "a.opus": { "start_ms": 1000, "end_ms": 3000, "times": 2 } }
+			},
+			"sfx": { "folder": "sfx", "start": "none", "states": { "none": {} } },
+			"crossfade_ms": 1500
+		}"#;
+
+		let scape = Soundscape::from_json(text).unwrap();
+		let points = scape.bgm.loop_points("bgm/a.opus").unwrap();
+
+		assert_eq!(points.extra(), Duration::from_secs(4));
+		assert_eq!(scape.crossfade, Some(Duration::from_millis(1500)));
+
+		let backwards = text.replace("\"end_ms\": 3000", "\"end_ms\": 500");
+		assert!(matches!(
+			Soundscape::from_json(&backwards),
+			Err(SoundscapeError::BadLoop(_))
+		));
+	}
+
+	#[test]
+	fn test_unknown_state_rejected() {
+		let text = r#"{
+			"bgm": {
+				"folder": "bgm",
+				"start": "none",
+				"states": { "none": {} },
+				"transitions": [{ "from": "none", "to": "nowhere", "on": "advance" }]
+			},
+			"sfx": { "folder": "sfx", "start": "none", "states": { "none": {} } }
+		}"#;
+
+		let err = Soundscape::from_json(text).unwrap_err();
+		assert!(matches!(err, SoundscapeError::UnknownState("bgm", _)));
+
+		let err = SoundscapeError::InPack("felyne".into(), Box::new(err));
+		assert_eq!(
+			err.to_string(),
+			"in pack 'felyne': no bgm state named 'nowhere'"
+		);
+	}
+}
@@ -0,0 +1,121 @@
+use super::{StoredTrace, TraceSink, TraceSinkError};
+use serenity::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Writes each trace to its own file in a local directory.
+///
+/// Traces are written to a hidden temporary file, synced, and then renamed
+/// into place so that readers never observe a partial trace.
+pub struct FsSink {
+	dir: PathBuf,
+}
+
+impl FsSink {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into() }
+	}
+}
+
+#[async_trait]
+impl TraceSink for FsSink {
+	async fn store(&self, key: &str, data: &[u8]) -> Result<(), TraceSinkError> {
+		fs::create_dir_all(&self.dir).await?;
+
+		let tmp_path = self.dir.join(format!(".{}.tmp", key));
+		let final_path = self.dir.join(key);
+
+		let mut file = fs::File::create(&tmp_path).await?;
+		file.write_all(data).await?;
+		file.sync_all().await?;
+		drop(file);
+
+		if let Err(e) = fs::rename(&tmp_path, &final_path).await {
+			let _ = fs::remove_file(&tmp_path).await;
+			return Err(e.into());
+		}
+
+		// Make sure the rename itself survives a crash.
+		fs::File::open(&self.dir).await?.sync_all().await?;
+
+		Ok(())
+	}
+
+	async fn list(&self) -> Result<Vec<StoredTrace>, TraceSinkError> {
+		let mut out = vec![];
+
+		let mut entries = match fs::read_dir(&self.dir).await {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(out),
+			Err(e) => return Err(e.into()),
+		};
+
+		while let Some(entry) = entries.next_entry().await? {
+			let key = match entry.file_name().into_string() {
+				Ok(name) if !name.starts_with('.') => name,
+				_ => continue,
+			};
+
+			let meta = entry.metadata().await?;
+			if !meta.is_file() {
+				continue;
+			}
+
+			out.push(StoredTrace {
+				key,
+				size: meta.len(),
+				created: meta.modified()?,
+			});
+		}
+
+		Ok(out)
+	}
+
+	async fn remove(&self, key: &str) -> Result<(), TraceSinkError> {
+		fs::remove_file(self.dir.join(key))
+			.await
+			.map_err(Into::into)
+	}
+
+	async fn archive(&self, key: &str, dir: &Path) -> Result<(), TraceSinkError> {
+		fs::create_dir_all(dir).await?;
+
+		let from = self.dir.join(key);
+		let to = dir.join(key);
+
+		// Renames can't cross filesystems, so fall back to a copy.
+		if fs::rename(&from, &to).await.is_err() {
+			fs::copy(&from, &to).await?;
+			fs::remove_file(&from).await?;
+		}
+
+		Ok(())
+	}
+
+	fn name(&self) -> &'static str {
+		"filesystem"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_store_leaves_only_final_file() {
+		let dir = std::env::temp_dir().join(format!("felyne-fs-sink-{}", std::process::id()));
+		let sink = FsSink::new(&dir);
+
+		sink.store("1.bc", b"mrow").await.unwrap();
+
+		let names: Vec<_> = std::fs::read_dir(&dir)
+			.unwrap()
+			.map(|e| e.unwrap().file_name().into_string().unwrap())
+			.collect();
+
+		assert_eq!(names, vec!["1.bc".to_string()]);
+		assert_eq!(std::fs::read(dir.join("1.bc")).unwrap(), b"mrow");
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+}
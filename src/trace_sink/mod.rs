@@ -0,0 +1,364 @@
+mod fs;
+mod postgres;
+pub mod retention;
+mod s3;
+
+pub use self::{fs::FsSink, postgres::PostgresSink, retention::TraceUsage, s3::S3Sink};
+
+use crate::{
+	config::{RetentionConfig, TraceConfig, TraceSinkConfig},
+	dbs::FelyneDb,
+};
+use felyne_trace::{traces::*, FelyneTrace};
+use parking_lot::RwLock;
+use serenity::{async_trait, prelude::TypeMapKey};
+use std::{
+	fmt,
+	io::Error as IoError,
+	path::Path,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::{Duration, SystemTime},
+};
+use tokio::sync::Notify;
+use tokio_postgres::Error as SqlError;
+use tracing::{error, warn};
+
+pub struct TraceSinkKey;
+
+impl TypeMapKey for TraceSinkKey {
+	type Value = Arc<TraceStore>;
+}
+
+/// A destination for finished, anonymised traces.
+#[async_trait]
+pub trait TraceSink: Send + Sync {
+	/// Stores an encoded trace under `key`, replacing any existing object.
+	async fn store(&self, key: &str, data: &[u8]) -> Result<(), TraceSinkError>;
+
+	/// Lists every stored trace, for retention checks.
+	async fn list(&self) -> Result<Vec<StoredTrace>, TraceSinkError> {
+		Err(TraceSinkError::Unsupported("listing traces"))
+	}
+
+	/// Deletes the trace stored under `key`.
+	async fn remove(&self, _key: &str) -> Result<(), TraceSinkError> {
+		Err(TraceSinkError::Unsupported("removing traces"))
+	}
+
+	/// Moves a trace out of the sink and into a local directory.
+	async fn archive(&self, _key: &str, _dir: &Path) -> Result<(), TraceSinkError> {
+		Err(TraceSinkError::Unsupported("archiving traces"))
+	}
+
+	fn name(&self) -> &'static str;
+}
+
+#[derive(Clone, Debug)]
+pub struct StoredTrace {
+	pub key: String,
+	pub size: u64,
+	pub created: SystemTime,
+}
+
+#[derive(Debug)]
+pub enum TraceSinkError {
+	Encode(IoError),
+	Io(IoError),
+	Sql(SqlError),
+	Http(reqwest::Error),
+	Status(u16, String),
+	Unsupported(&'static str),
+}
+
+impl fmt::Display for TraceSinkError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Encode(e) => write!(f, "couldn't encode trace: {}", e),
+			Self::Io(e) => write!(f, "I/O error: {}", e),
+			Self::Sql(e) => write!(f, "database error: {}", e),
+			Self::Http(e) => write!(f, "HTTP error: {}", e),
+			Self::Status(code, body) => write!(f, "server replied {}: {}", code, body),
+			Self::Unsupported(what) => write!(f, "{} is not supported by this sink", what),
+		}
+	}
+}
+
+impl From<IoError> for TraceSinkError {
+	fn from(e: IoError) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<SqlError> for TraceSinkError {
+	fn from(e: SqlError) -> Self {
+		Self::Sql(e)
+	}
+}
+
+impl From<reqwest::Error> for TraceSinkError {
+	fn from(e: reqwest::Error) -> Self {
+		Self::Http(e)
+	}
+}
+
+/// Summary of a stored trace, as recorded in the `trace_manifest` table.
+#[derive(Clone, Debug)]
+pub struct TraceManifest {
+	pub key: String,
+	pub label: i32,
+	pub length_ns: i64,
+	pub total_user_count: i64,
+	pub starting_user_count: i64,
+	pub optout_user_count: i64,
+	pub server: Option<String>,
+	pub region: Option<String>,
+	pub version: i32,
+}
+
+impl TraceManifest {
+	pub fn new(key: &str, trace: &FelyneTrace) -> Option<Self> {
+		let out = match trace {
+			FelyneTrace::Vers1(FelyneTraceV1 {
+				length,
+				label,
+				region,
+				optout_users,
+				total_user_count,
+				starting_user_count,
+				..
+			}) => Self {
+				key: key.to_string(),
+				label: *label as i32,
+				length_ns: *length as i64,
+				total_user_count: *total_user_count as i64,
+				starting_user_count: *starting_user_count as i64,
+				optout_user_count: optout_users.len() as i64,
+				server: None,
+				region: region.clone(),
+				version: 1,
+			},
+			FelyneTrace::Vers2(FelyneTraceV2 {
+				length,
+				label,
+				region,
+				region_override,
+				server,
+				optout_users,
+				total_user_count,
+				starting_user_count,
+				..
+			}) => Self {
+				key: key.to_string(),
+				label: *label as i32,
+				length_ns: *length as i64,
+				total_user_count: *total_user_count as i64,
+				starting_user_count: *starting_user_count as i64,
+				optout_user_count: optout_users.len() as i64,
+				server: server.clone(),
+				region: region_override.clone().or_else(|| region.clone()),
+				version: 2,
+			},
+			_ => return None,
+		};
+
+		Some(out)
+	}
+}
+
+/// Encodes traces and hands them to the configured [`TraceSink`], retrying on failure.
+pub struct TraceStore {
+	sink: Box<dyn TraceSink>,
+	retries: u32,
+	backoff: Duration,
+	pending: Arc<PendingWrites>,
+	retention: RetentionConfig,
+	usage: RwLock<TraceUsage>,
+}
+
+#[derive(Default)]
+struct PendingWrites {
+	count: AtomicUsize,
+	idle: Notify,
+}
+
+/// Marks a trace write as in-flight until dropped, so that [`TraceStore::flush`] can wait on it.
+pub struct WriteGuard(Arc<PendingWrites>);
+
+impl Drop for WriteGuard {
+	fn drop(&mut self) {
+		if self.0.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+			self.0.idle.notify_waiters();
+		}
+	}
+}
+
+impl TraceStore {
+	pub fn new(config: &TraceConfig, db: Arc<FelyneDb>) -> Self {
+		let sink: Box<dyn TraceSink> = match &config.sink {
+			TraceSinkConfig::Fs { dir } => Box::new(FsSink::new(dir)),
+			TraceSinkConfig::Postgres => Box::new(PostgresSink::new(db)),
+			TraceSinkConfig::S3(s3) => Box::new(S3Sink::new(s3.clone())),
+		};
+
+		Self {
+			sink,
+			retries: config.retries,
+			backoff: Duration::from_millis(config.retry_backoff_ms),
+			pending: Default::default(),
+			retention: config.retention.clone(),
+			usage: Default::default(),
+		}
+	}
+
+	/// A store which files traces under `dir`, without retrying.
+	#[cfg(test)]
+	pub fn in_dir(dir: impl Into<std::path::PathBuf>) -> Self {
+		Self {
+			sink: Box::new(FsSink::new(dir)),
+			retries: 0,
+			backoff: Duration::ZERO,
+			pending: Default::default(),
+			retention: Default::default(),
+			usage: Default::default(),
+		}
+	}
+
+	pub fn retention(&self) -> &RetentionConfig {
+		&self.retention
+	}
+
+	pub fn usage(&self) -> TraceUsage {
+		self.usage.read().clone()
+	}
+
+	/// Whether new captures should be refused, as of the last retention pass.
+	pub fn over_quota(&self) -> bool {
+		self.usage.read().over_quota
+	}
+
+	/// Registers an upcoming write, which [`flush`] will wait for.
+	///
+	/// This should be called *before* spawning the task which finalises a trace.
+	///
+	/// [`flush`]: TraceStore::flush
+	pub fn begin_write(&self) -> WriteGuard {
+		self.pending.count.fetch_add(1, Ordering::AcqRel);
+		WriteGuard(self.pending.clone())
+	}
+
+	/// Waits until every registered write has completed or failed.
+	pub async fn flush(&self) {
+		loop {
+			let idle = self.pending.idle.notified();
+
+			if self.pending.count.load(Ordering::Acquire) == 0 {
+				return;
+			}
+
+			idle.await;
+		}
+	}
+
+	/// Writes `trace` under `key`, returning the last error seen if every attempt fails.
+	pub async fn save(&self, key: &str, trace: &FelyneTrace) -> Result<(), TraceSinkError> {
+		let data = felyne_trace::write_async(Vec::new(), trace)
+			.await
+			.map_err(TraceSinkError::Encode)?;
+
+		let mut attempt = 0;
+
+		loop {
+			match self.sink.store(key, &data).await {
+				Ok(()) => {
+					self.usage.write().add(data.len() as u64, &self.retention);
+					return Ok(());
+				},
+				Err(e) if attempt < self.retries => {
+					warn!(
+						"Failed to store trace {} in {} sink (attempt {}): {}",
+						key,
+						self.sink.name(),
+						attempt + 1,
+						e
+					);
+
+					tokio::time::sleep(self.backoff * 2u32.saturating_pow(attempt)).await;
+					attempt += 1;
+				},
+				Err(e) => {
+					error!(
+						"Gave up storing trace {} in {} sink after {} attempts: {}",
+						key,
+						self.sink.name(),
+						attempt + 1,
+						e
+					);
+
+					return Err(e);
+				},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_flush_waits_for_open_writes() {
+		let store = Arc::new(TraceStore::in_dir(
+			std::env::temp_dir().join("felyne-flush-test"),
+		));
+
+		// Nothing registered: returns straight away.
+		tokio::time::timeout(Duration::from_secs(1), store.flush())
+			.await
+			.expect("flush with no writes should not block");
+
+		let guard = store.begin_write();
+		let flush = tokio::spawn({
+			let store = store.clone();
+			async move { store.flush().await }
+		});
+
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		assert!(!flush.is_finished());
+
+		drop(guard);
+
+		tokio::time::timeout(Duration::from_secs(1), flush)
+			.await
+			.expect("flush should finish once the write is dropped")
+			.unwrap();
+	}
+
+	#[test]
+	fn test_manifest_prefers_region_override() {
+		let trace = FelyneTrace::Vers2(FelyneTraceV2 {
+			events: vec![],
+			length: 5_000_000_000,
+			label: felyne_trace::Label::Gaming,
+			region: Some("us-east".into()),
+			region_override: Some("rotterdam".into()),
+			server: Some("rotterdam1234.discord.media".into()),
+			optout_users: vec![1, 2],
+			total_user_count: 6,
+			starting_user_count: 3,
+		});
+
+		let manifest = TraceManifest::new("1.bc", &trace).unwrap();
+
+		assert_eq!(manifest.key, "1.bc");
+		assert_eq!(manifest.label, felyne_trace::Label::Gaming as i32);
+		assert_eq!(manifest.length_ns, 5_000_000_000);
+		assert_eq!(manifest.optout_user_count, 2);
+		assert_eq!(manifest.total_user_count, 6);
+		assert_eq!(manifest.starting_user_count, 3);
+		assert_eq!(manifest.region.as_deref(), Some("rotterdam"));
+		assert_eq!(manifest.version, 2);
+	}
+}
@@ -0,0 +1,44 @@
+use super::{StoredTrace, TraceSink, TraceSinkError};
+use crate::dbs::FelyneDb;
+use serenity::async_trait;
+use std::{path::Path, sync::Arc};
+use tokio::fs;
+
+/// Stores traces as `bytea` rows in the bot's own database.
+pub struct PostgresSink {
+	db: Arc<FelyneDb>,
+}
+
+impl PostgresSink {
+	pub fn new(db: Arc<FelyneDb>) -> Self {
+		Self { db }
+	}
+}
+
+#[async_trait]
+impl TraceSink for PostgresSink {
+	async fn store(&self, key: &str, data: &[u8]) -> Result<(), TraceSinkError> {
+		self.db.upsert_trace(key, data).await.map_err(Into::into)
+	}
+
+	async fn list(&self) -> Result<Vec<StoredTrace>, TraceSinkError> {
+		self.db.select_traces().await.map_err(Into::into)
+	}
+
+	async fn remove(&self, key: &str) -> Result<(), TraceSinkError> {
+		self.db.delete_trace(key).await.map_err(Into::into)
+	}
+
+	async fn archive(&self, key: &str, dir: &Path) -> Result<(), TraceSinkError> {
+		let data = self.db.select_trace_data(key).await?;
+
+		fs::create_dir_all(dir).await?;
+		fs::write(dir.join(key), data).await?;
+
+		self.remove(key).await
+	}
+
+	fn name(&self) -> &'static str {
+		"postgres"
+	}
+}
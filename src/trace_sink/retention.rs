@@ -0,0 +1,225 @@
+use super::{StoredTrace, TraceStore};
+use crate::config::RetentionConfig;
+use std::{
+	path::Path,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+use tracing::{error, info, warn};
+
+#[derive(Clone, Debug, Default)]
+pub struct TraceUsage {
+	pub files: usize,
+	pub bytes: u64,
+	pub oldest: Option<SystemTime>,
+	pub last_scan: Option<SystemTime>,
+	pub over_quota: bool,
+}
+
+impl TraceUsage {
+	pub(super) fn add(&mut self, bytes: u64, rules: &RetentionConfig) {
+		self.files += 1;
+		self.bytes += bytes;
+		self.over_quota = rules.refuse_when_full && rules.over_quota(self.files, self.bytes);
+	}
+}
+
+/// Traces to prune, and the usage which will remain afterwards.
+#[derive(Debug)]
+pub struct RetentionPlan {
+	pub expired: Vec<StoredTrace>,
+	pub evicted: Vec<StoredTrace>,
+	pub remaining: TraceUsage,
+}
+
+/// Decides which traces break the retention rules, oldest first.
+pub fn plan(
+	mut traces: Vec<StoredTrace>,
+	rules: &RetentionConfig,
+	now: SystemTime,
+) -> RetentionPlan {
+	traces.sort_by_key(|t| t.created);
+
+	let max_age = rules.max_age_secs.map(Duration::from_secs);
+	let (expired, mut kept): (Vec<_>, Vec<_>) = traces.into_iter().partition(|t| {
+		max_age
+			.map(|age| now.duration_since(t.created).unwrap_or_default() > age)
+			.unwrap_or(false)
+	});
+
+	let mut bytes: u64 = kept.iter().map(|t| t.size).sum();
+	let mut evict_count = 0;
+
+	if !rules.refuse_when_full {
+		while evict_count < kept.len() && rules.over_quota(kept.len() - evict_count, bytes) {
+			bytes -= kept[evict_count].size;
+			evict_count += 1;
+		}
+	}
+
+	let evicted = kept.drain(..evict_count).collect();
+
+	RetentionPlan {
+		expired,
+		evicted,
+		remaining: TraceUsage {
+			files: kept.len(),
+			bytes,
+			oldest: kept.first().map(|t| t.created),
+			last_scan: Some(now),
+			over_quota: rules.over_quota(kept.len(), bytes),
+		},
+	}
+}
+
+/// Applies the retention rules once, updating the store's view of its usage.
+pub async fn enforce(store: &TraceStore) {
+	let traces = match store.sink.list().await {
+		Ok(t) => t,
+		Err(e) => {
+			warn!(
+				"Couldn't list traces in {} sink for retention: {}",
+				store.sink.name(),
+				e
+			);
+			return;
+		},
+	};
+
+	let mut plan = plan(traces, &store.retention, SystemTime::now());
+	let archive = store.retention.archive_dir.as_ref().map(Path::new);
+
+	for trace in plan.expired.iter().chain(plan.evicted.iter()) {
+		let res = match archive {
+			Some(dir) => store.sink.archive(&trace.key, dir).await,
+			None => store.sink.remove(&trace.key).await,
+		};
+
+		if let Err(e) = res {
+			error!("Failed to prune trace {}: {}", trace.key, e);
+
+			// It's still taking up space.
+			plan.remaining.files += 1;
+			plan.remaining.bytes += trace.size;
+			plan.remaining.oldest = Some(
+				plan.remaining
+					.oldest
+					.map_or(trace.created, |t| t.min(trace.created)),
+			);
+		}
+	}
+
+	let pruned = plan.expired.len() + plan.evicted.len();
+	if pruned > 0 {
+		info!("Pruned {} traces from {} sink.", pruned, store.sink.name());
+	}
+
+	plan.remaining.over_quota = store
+		.retention
+		.over_quota(plan.remaining.files, plan.remaining.bytes);
+
+	if plan.remaining.over_quota {
+		warn!(
+			"Trace storage is over quota ({} files, {} bytes): new captures will be refused.",
+			plan.remaining.files, plan.remaining.bytes
+		);
+	}
+
+	*store.usage.write() = plan.remaining;
+}
+
+/// Periodically enforces retention rules, if any are set.
+pub fn spawn_retention_task(store: Arc<TraceStore>) {
+	if !store.retention.is_enforced() {
+		return;
+	}
+
+	let interval = Duration::from_secs(store.retention.interval_secs.max(1));
+
+	tokio::spawn(async move {
+		loop {
+			enforce(&store).await;
+			tokio::time::sleep(interval).await;
+		}
+	});
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn trace(key: &str, size: u64, age_secs: u64, now: SystemTime) -> StoredTrace {
+		StoredTrace {
+			key: key.into(),
+			size,
+			created: now - Duration::from_secs(age_secs),
+		}
+	}
+
+	fn keys(traces: &[StoredTrace]) -> Vec<&str> {
+		traces.iter().map(|t| t.key.as_str()).collect()
+	}
+
+	#[test]
+	fn test_expires_old_traces() {
+		let now = SystemTime::now();
+		let rules = RetentionConfig {
+			max_age_secs: Some(100),
+			..Default::default()
+		};
+
+		let out = plan(
+			vec![trace("new", 1, 10, now), trace("old", 1, 200, now)],
+			&rules,
+			now,
+		);
+
+		assert_eq!(keys(&out.expired), vec!["old"]);
+		assert!(out.evicted.is_empty());
+		assert_eq!(out.remaining.files, 1);
+	}
+
+	#[test]
+	fn test_evicts_oldest_until_under_quota() {
+		let now = SystemTime::now();
+		let rules = RetentionConfig {
+			max_total_bytes: Some(10),
+			max_files: Some(2),
+			..Default::default()
+		};
+
+		let out = plan(
+			vec![
+				trace("b", 4, 20, now),
+				trace("a", 4, 30, now),
+				trace("c", 4, 10, now),
+				trace("d", 4, 5, now),
+			],
+			&rules,
+			now,
+		);
+
+		assert_eq!(keys(&out.evicted), vec!["a", "b"]);
+		assert_eq!(out.remaining.bytes, 8);
+		assert!(!out.remaining.over_quota);
+	}
+
+	#[test]
+	fn test_refuse_when_full_keeps_traces() {
+		let now = SystemTime::now();
+		let rules = RetentionConfig {
+			max_files: Some(1),
+			refuse_when_full: true,
+			..Default::default()
+		};
+
+		let out = plan(
+			vec![trace("a", 1, 20, now), trace("b", 1, 10, now)],
+			&rules,
+			now,
+		);
+
+		assert!(out.evicted.is_empty());
+		assert!(out.remaining.over_quota);
+	}
+}
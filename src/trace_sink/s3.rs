@@ -0,0 +1,387 @@
+use super::{StoredTrace, TraceSink, TraceSinkError};
+use crate::config::S3Config;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, Response, Url};
+use serenity::async_trait;
+use sha2::{Digest, Sha256};
+use std::{fmt::Write, path::Path, time::SystemTime};
+use tokio::fs;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Uploads traces to an S3-compatible object store (AWS, MinIO, ...).
+///
+/// Requests use path-style addressing and are signed with AWS Signature V4.
+pub struct S3Sink {
+	config: S3Config,
+	client: Client,
+}
+
+impl S3Sink {
+	pub fn new(config: S3Config) -> Self {
+		Self {
+			config,
+			client: Client::new(),
+		}
+	}
+
+	fn bucket_path(&self) -> String {
+		format!("/{}", uri_encode(&self.config.bucket))
+	}
+
+	fn object_path(&self, key: &str) -> String {
+		format!(
+			"{}/{}{}",
+			self.bucket_path(),
+			uri_encode(&self.config.prefix),
+			uri_encode(key)
+		)
+	}
+
+	fn authorization(
+		&self,
+		method: &Method,
+		path: &str,
+		query: &str,
+		host: &str,
+		payload_hash: &str,
+		now: DateTime<Utc>,
+	) -> (String, String) {
+		let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+		let date = now.format("%Y%m%d").to_string();
+		let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+		let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+		let canonical_request = format!(
+			"{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+			method, path, query, host, payload_hash, amz_date, signed_headers, payload_hash
+		);
+
+		let string_to_sign = format!(
+			"AWS4-HMAC-SHA256\n{}\n{}\n{}",
+			amz_date,
+			scope,
+			to_hex(&Sha256::digest(canonical_request.as_bytes()))
+		);
+
+		let key = signing_key(&self.config.secret_key, &date, &self.config.region, "s3");
+		let signature = to_hex(&hmac(&key, string_to_sign.as_bytes()));
+
+		let auth = format!(
+			"AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+			self.config.access_key, scope, signed_headers, signature
+		);
+
+		(auth, amz_date)
+	}
+
+	/// Sends a signed request, turning any non-2xx reply into an error.
+	async fn request(
+		&self,
+		method: Method,
+		path: &str,
+		query: &[(&str, &str)],
+		body: Vec<u8>,
+	) -> Result<Response, TraceSinkError> {
+		let query = canonical_query(query);
+		let mut url = Url::parse(self.config.endpoint.trim_end_matches('/'))
+			.and_then(|base| base.join(path))
+			.map_err(|e| TraceSinkError::Status(0, format!("Bad S3 endpoint: {}", e)))?;
+		url.set_query(Some(&query).filter(|q| !q.is_empty()).map(String::as_str));
+
+		let host = match (url.host_str(), url.port()) {
+			(Some(h), Some(p)) => format!("{}:{}", h, p),
+			(Some(h), None) => h.to_string(),
+			_ => return Err(TraceSinkError::Status(0, "S3 endpoint has no host".into())),
+		};
+
+		let payload_hash = to_hex(&Sha256::digest(&body));
+		let (auth, amz_date) =
+			self.authorization(&method, path, &query, &host, &payload_hash, Utc::now());
+
+		let resp = self
+			.client
+			.request(method, url)
+			.header("authorization", auth)
+			.header("x-amz-content-sha256", payload_hash)
+			.header("x-amz-date", amz_date)
+			.body(body)
+			.send()
+			.await?;
+
+		let status = resp.status();
+		if status.is_success() {
+			Ok(resp)
+		} else {
+			let body = resp.text().await.unwrap_or_default();
+			Err(TraceSinkError::Status(status.as_u16(), body))
+		}
+	}
+}
+
+#[async_trait]
+impl TraceSink for S3Sink {
+	async fn store(&self, key: &str, data: &[u8]) -> Result<(), TraceSinkError> {
+		self.request(Method::PUT, &self.object_path(key), &[], data.to_vec())
+			.await
+			.map(|_| ())
+	}
+
+	async fn list(&self) -> Result<Vec<StoredTrace>, TraceSinkError> {
+		let mut out = vec![];
+		let mut token: Option<String> = None;
+
+		// ListObjectsV2 pages its results, 1000 objects at a time.
+		loop {
+			let mut query = vec![("list-type", "2"), ("prefix", self.config.prefix.as_str())];
+			if let Some(token) = &token {
+				query.push(("continuation-token", token.as_str()));
+			}
+
+			let body = self
+				.request(Method::GET, &self.bucket_path(), &query, vec![])
+				.await?
+				.text()
+				.await?;
+
+			let (mut page, next) = parse_listing(&body, &self.config.prefix);
+			out.append(&mut page);
+
+			match next {
+				Some(next) => token = Some(next),
+				None => return Ok(out),
+			}
+		}
+	}
+
+	async fn remove(&self, key: &str) -> Result<(), TraceSinkError> {
+		self.request(Method::DELETE, &self.object_path(key), &[], vec![])
+			.await
+			.map(|_| ())
+	}
+
+	async fn archive(&self, key: &str, dir: &Path) -> Result<(), TraceSinkError> {
+		let data = self
+			.request(Method::GET, &self.object_path(key), &[], vec![])
+			.await?
+			.bytes()
+			.await?;
+
+		fs::create_dir_all(dir).await?;
+		fs::write(dir.join(key), &data).await?;
+
+		self.remove(key).await
+	}
+
+	fn name(&self) -> &'static str {
+		"s3"
+	}
+}
+
+/// Reads the traces (and the token for the next page, if any) out of a
+/// ListObjectsV2 reply.
+fn parse_listing(xml: &str, prefix: &str) -> (Vec<StoredTrace>, Option<String>) {
+	let traces = xml_tags(xml, "Contents")
+		.filter_map(|obj| {
+			let key = xml_unescape(xml_tag(obj, "Key")?);
+			let size = xml_tag(obj, "Size")?.trim().parse().ok()?;
+			let created =
+				DateTime::parse_from_rfc3339(xml_tag(obj, "LastModified")?.trim()).ok()?;
+
+			Some(StoredTrace {
+				key: key.strip_prefix(prefix).unwrap_or(&key).to_string(),
+				size,
+				created: SystemTime::from(created),
+			})
+		})
+		.collect();
+
+	let next = xml_tag(xml, "IsTruncated")
+		.filter(|t| t.trim() == "true")
+		.and_then(|_| xml_tag(xml, "NextContinuationToken"))
+		.map(xml_unescape);
+
+	(traces, next)
+}
+
+/// The contents of each `<tag>...</tag>` in `xml`, outermost first.
+fn xml_tags<'a>(xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+	let open = format!("<{}>", tag);
+	let close = format!("</{}>", tag);
+	let mut rest = xml;
+
+	std::iter::from_fn(move || {
+		let start = rest.find(&open)? + open.len();
+		let len = rest[start..].find(&close)?;
+		let inner = &rest[start..start + len];
+		rest = &rest[start + len + close.len()..];
+
+		Some(inner)
+	})
+}
+
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+	xml_tags(xml, tag).next()
+}
+
+fn xml_unescape(s: &str) -> String {
+	s.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&apos;", "'")
+		.replace("&amp;", "&")
+}
+
+/// Sorts and encodes query parameters as SigV4 expects to sign them.
+fn canonical_query(params: &[(&str, &str)]) -> String {
+	let mut params: Vec<_> = params
+		.iter()
+		.map(|(k, v)| (query_encode(k), query_encode(v)))
+		.collect();
+	params.sort();
+
+	params
+		.iter()
+		.map(|(k, v)| format!("{}={}", k, v))
+		.collect::<Vec<_>>()
+		.join("&")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+	let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+	mac.update(data);
+	mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+	let k_date = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+	let k_region = hmac(&k_date, region.as_bytes());
+	let k_service = hmac(&k_region, service.as_bytes());
+	hmac(&k_service, b"aws4_request")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().fold(String::new(), |mut out, b| {
+		let _ = write!(out, "{:02x}", b);
+		out
+	})
+}
+
+/// Percent-encodes everything but unreserved characters and `/`, per SigV4.
+fn uri_encode(s: &str) -> String {
+	s.bytes().fold(String::new(), |mut out, b| {
+		match b {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' =>
+				out.push(b as char),
+			_ => {
+				let _ = write!(out, "%{:02X}", b);
+			},
+		}
+		out
+	})
+}
+
+/// As [`uri_encode`], but for query strings, where `/` must be encoded too.
+fn query_encode(s: &str) -> String {
+	uri_encode(s).replace('/', "%2F")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_signing_key_matches_aws_example() {
+		// From the AWS SigV4 documentation.
+		let key = signing_key(
+			"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+			"20120215",
+			"us-east-1",
+			"iam",
+		);
+
+		assert_eq!(
+			to_hex(&key),
+			"f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+		);
+	}
+
+	#[test]
+	fn test_canonical_query_sorts_and_encodes() {
+		assert_eq!(
+			canonical_query(&[("prefix", "test/a b"), ("list-type", "2")]),
+			"list-type=2&prefix=test%2Fa%20b"
+		);
+	}
+
+	#[test]
+	fn test_parse_listing_strips_prefix_and_pages() {
+		let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+			<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+				<Name>felyne-traces</Name>
+				<Prefix>test/</Prefix>
+				<IsTruncated>true</IsTruncated>
+				<NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+				<Contents>
+					<Key>test/1.bc</Key>
+					<LastModified>2024-03-01T12:00:00.000Z</LastModified>
+					<Size>1024</Size>
+				</Contents>
+				<Contents>
+					<Key>test/2.bc</Key>
+					<LastModified>2024-03-02T12:00:00.000Z</LastModified>
+					<Size>2048</Size>
+				</Contents>
+			</ListBucketResult>"#;
+
+		let (traces, next) = parse_listing(xml, "test/");
+
+		assert_eq!(
+			traces.iter().map(|t| t.key.as_str()).collect::<Vec<_>>(),
+			["1.bc", "2.bc"]
+		);
+		assert_eq!(traces[1].size, 2048);
+		assert_eq!(
+			traces[0].created,
+			SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_709_294_400)
+		);
+		assert_eq!(
+			next.as_deref(),
+			Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=")
+		);
+
+		let last = xml.replace("<IsTruncated>true", "<IsTruncated>false");
+		assert_eq!(parse_listing(&last, "test/").1, None);
+	}
+
+	/// Run against a local MinIO, e.g. `docker run -p 9000:9000 minio/minio server /data`,
+	/// with a `felyne-traces` bucket and the default `minioadmin` credentials.
+	#[tokio::test]
+	#[ignore]
+	async fn test_minio_round_trip() {
+		let sink = S3Sink::new(S3Config {
+			endpoint: "http://localhost:9000".into(),
+			bucket: "felyne-traces".into(),
+			region: "us-east-1".into(),
+			access_key: "minioadmin".into(),
+			secret_key: "minioadmin".into(),
+			prefix: "test/".into(),
+		});
+
+		sink.store("minio-test.bc", b"mrow").await.unwrap();
+
+		let listed = sink.list().await.unwrap();
+		assert!(listed
+			.iter()
+			.any(|t| t.key == "minio-test.bc" && t.size == 4));
+
+		sink.remove("minio-test.bc").await.unwrap();
+		assert!(sink
+			.list()
+			.await
+			.unwrap()
+			.iter()
+			.all(|t| t.key != "minio-test.bc"));
+	}
+}
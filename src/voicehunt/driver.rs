@@ -0,0 +1,352 @@
+use crate::audio_resources::CachedSound;
+use serenity::{async_trait, model::prelude::ChannelId};
+use songbird::{
+	error::TrackResult,
+	events::{Event, EventHandler},
+	tracks::{TrackHandle, TrackState},
+	Call,
+	Driver,
+};
+use std::time::Duration;
+
+/// A voice connection which Felyne can hunt through.
+///
+/// This is songbird's `Call` in practice, but can be swapped out so that
+/// `felyne_life` runs without Discord.
+#[async_trait]
+pub trait VoiceDriver: Send + 'static {
+	type Track: VoiceTrack;
+
+	/// Connects to `channel`, returning whether that went through.
+	async fn join(&mut self, channel: ChannelId) -> bool;
+
+	async fn leave(&mut self);
+
+	/// Starts `sound` (cached from `file`) over anything already playing.
+	fn play(&mut self, file: &str, sound: &CachedSound) -> Self::Track;
+
+	/// Stops every playing track.
+	fn stop(&mut self);
+
+	fn add_global_event<F: EventHandler + 'static>(&mut self, event: Event, action: F);
+
+	fn remove_all_global_events(&mut self);
+}
+
+/// A track started by a `VoiceDriver`.
+#[async_trait]
+pub trait VoiceTrack: Clone + Send + Sync + 'static {
+	/// Tells this track apart from any other played through the same driver.
+	fn id(&self) -> u128;
+
+	fn set_volume(&self, volume: f32) -> TrackResult<()>;
+
+	/// Multiplies the track's volume by `by`, as it stands when the change lands.
+	fn scale_volume(&self, by: f32) -> TrackResult<()>;
+
+	fn pause(&self) -> TrackResult<()>;
+
+	fn stop(&self) -> TrackResult<()>;
+
+	fn seek(&self, position: Duration) -> TrackResult<()>;
+
+	fn add_event<F: EventHandler + 'static>(&self, event: Event, action: F) -> TrackResult<()>;
+
+	async fn get_info(&self) -> TrackResult<TrackState>;
+}
+
+#[async_trait]
+impl VoiceDriver for Call {
+	type Track = TrackHandle;
+
+	async fn join(&mut self, channel: ChannelId) -> bool {
+		Call::join(self, channel).await.is_ok()
+	}
+
+	async fn leave(&mut self) {
+		let _ = Call::leave(self).await;
+	}
+
+	fn play(&mut self, _file: &str, sound: &CachedSound) -> TrackHandle {
+		Driver::play_input(self, sound.into())
+	}
+
+	fn stop(&mut self) {
+		Driver::stop(self)
+	}
+
+	fn add_global_event<F: EventHandler + 'static>(&mut self, event: Event, action: F) {
+		Driver::add_global_event(self, event, action)
+	}
+
+	fn remove_all_global_events(&mut self) {
+		Driver::remove_all_global_events(self)
+	}
+}
+
+#[async_trait]
+impl VoiceTrack for TrackHandle {
+	fn id(&self) -> u128 {
+		self.uuid().as_u128()
+	}
+
+	fn set_volume(&self, volume: f32) -> TrackResult<()> {
+		TrackHandle::set_volume(self, volume)
+	}
+
+	fn scale_volume(&self, by: f32) -> TrackResult<()> {
+		self.action(move |true_track| {
+			*true_track.volume *= by;
+
+			None
+		})
+	}
+
+	fn pause(&self) -> TrackResult<()> {
+		TrackHandle::pause(self)
+	}
+
+	fn stop(&self) -> TrackResult<()> {
+		TrackHandle::stop(self)
+	}
+
+	fn seek(&self, position: Duration) -> TrackResult<()> {
+		// Felyne never needs to wait on where the seek lands.
+		let _ = TrackHandle::seek(self, position);
+		Ok(())
+	}
+
+	fn add_event<F: EventHandler + 'static>(&self, event: Event, action: F) -> TrackResult<()> {
+		TrackHandle::add_event(self, event, action)
+	}
+
+	async fn get_info(&self) -> TrackResult<TrackState> {
+		TrackHandle::get_info(self).await
+	}
+}
+
+#[cfg(test)]
+pub mod fake {
+	//! A `VoiceDriver` which only writes down what it was asked to do, and
+	//! whose tracks only end when a test says so.
+
+	use super::*;
+	use parking_lot::Mutex;
+	use songbird::{
+		error::ControlError,
+		events::{EventContext, TrackEvent},
+	};
+	use std::sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc,
+	};
+
+	/// Something `felyne_life` asked of its driver.
+	#[derive(Clone, Debug, PartialEq)]
+	pub enum Action {
+		Join(ChannelId),
+		Leave,
+		Play(String),
+		StopAll,
+	}
+
+	struct TrackInner {
+		file: String,
+		volume: f32,
+		done: bool,
+		events: Vec<(Event, Box<dyn EventHandler>)>,
+	}
+
+	#[derive(Clone)]
+	pub struct FakeTrack {
+		id: u64,
+		inner: Arc<Mutex<TrackInner>>,
+	}
+
+	impl FakeTrack {
+		pub fn file(&self) -> String {
+			self.inner.lock().file.clone()
+		}
+
+		pub fn volume(&self) -> f32 {
+			self.inner.lock().volume
+		}
+
+		pub fn is_done(&self) -> bool {
+			self.inner.lock().done
+		}
+
+		/// Plays out the track, firing any end events.
+		pub async fn finish(&self) {
+			fire(self.end(), TrackEvent::End).await;
+		}
+
+		/// Breaks the track, firing any error events.
+		pub async fn fail(&self) {
+			fire(self.end(), TrackEvent::Error).await;
+		}
+
+		fn end(&self) -> Vec<(Event, Box<dyn EventHandler>)> {
+			let mut inner = self.inner.lock();
+			inner.done = true;
+			std::mem::take(&mut inner.events)
+		}
+
+		fn control(&self, f: impl FnOnce(&mut TrackInner)) -> TrackResult<()> {
+			let mut inner = self.inner.lock();
+
+			if inner.done {
+				Err(ControlError::Finished)
+			} else {
+				f(&mut inner);
+				Ok(())
+			}
+		}
+	}
+
+	async fn fire(events: Vec<(Event, Box<dyn EventHandler>)>, kind: TrackEvent) {
+		for (event, handler) in events {
+			if event == Event::Track(kind) {
+				handler.act(&EventContext::Track(&[])).await;
+			}
+		}
+	}
+
+	#[async_trait]
+	impl VoiceTrack for FakeTrack {
+		fn id(&self) -> u128 {
+			u128::from(self.id)
+		}
+
+		fn set_volume(&self, volume: f32) -> TrackResult<()> {
+			self.control(|t| t.volume = volume)
+		}
+
+		fn scale_volume(&self, by: f32) -> TrackResult<()> {
+			self.control(|t| t.volume *= by)
+		}
+
+		fn pause(&self) -> TrackResult<()> {
+			self.control(|_| {})
+		}
+
+		/// Like songbird, stopping a track counts as it ending.
+		fn stop(&self) -> TrackResult<()> {
+			if self.is_done() {
+				return Err(ControlError::Finished);
+			}
+
+			tokio::spawn(fire(self.end(), TrackEvent::End));
+			Ok(())
+		}
+
+		fn seek(&self, _position: Duration) -> TrackResult<()> {
+			self.control(|_| {})
+		}
+
+		fn add_event<F: EventHandler + 'static>(&self, event: Event, action: F) -> TrackResult<()> {
+			self.control(|t| t.events.push((event, Box::new(action))))
+		}
+
+		async fn get_info(&self) -> TrackResult<TrackState> {
+			let inner = self.inner.lock();
+
+			if inner.done {
+				Err(ControlError::Finished)
+			} else {
+				Ok(TrackState {
+					volume: inner.volume,
+					..Default::default()
+				})
+			}
+		}
+	}
+
+	/// A shared record of a `FakeDriver`'s actions and tracks, which tests
+	/// can keep hold of while `felyne_life` owns the driver.
+	#[derive(Clone, Default)]
+	pub struct FakeLog {
+		actions: Arc<Mutex<Vec<Action>>>,
+		tracks: Arc<Mutex<Vec<FakeTrack>>>,
+		refuse: Arc<AtomicBool>,
+	}
+
+	impl FakeLog {
+		pub fn actions(&self) -> Vec<Action> {
+			self.actions.lock().clone()
+		}
+
+		/// Every track started so far, oldest first.
+		pub fn tracks(&self) -> Vec<FakeTrack> {
+			self.tracks.lock().clone()
+		}
+
+		/// The most recent unfinished track whose file starts with `prefix`.
+		pub fn playing(&self, prefix: &str) -> Option<FakeTrack> {
+			self.tracks
+				.lock()
+				.iter()
+				.rev()
+				.find(|t| !t.is_done() && t.file().starts_with(prefix))
+				.cloned()
+		}
+
+		/// Sets whether joining a channel should fail.
+		pub fn refuse(&self, refuse: bool) {
+			self.refuse.store(refuse, Ordering::Relaxed);
+		}
+
+		fn push(&self, action: Action) {
+			self.actions.lock().push(action);
+		}
+	}
+
+	#[derive(Default)]
+	pub struct FakeDriver {
+		pub log: FakeLog,
+		next_id: AtomicU64,
+	}
+
+	#[async_trait]
+	impl VoiceDriver for FakeDriver {
+		type Track = FakeTrack;
+
+		async fn join(&mut self, channel: ChannelId) -> bool {
+			self.log.push(Action::Join(channel));
+			!self.log.refuse.load(Ordering::Relaxed)
+		}
+
+		async fn leave(&mut self) {
+			self.log.push(Action::Leave);
+		}
+
+		fn play(&mut self, file: &str, _sound: &CachedSound) -> FakeTrack {
+			self.log.push(Action::Play(file.to_string()));
+
+			let track = FakeTrack {
+				id: self.next_id.fetch_add(1, Ordering::Relaxed),
+				inner: Arc::new(Mutex::new(TrackInner {
+					file: file.to_string(),
+					volume: 1.0,
+					done: false,
+					events: vec![],
+				})),
+			};
+			self.log.tracks.lock().push(track.clone());
+
+			track
+		}
+
+		fn stop(&mut self) {
+			self.log.push(Action::StopAll);
+
+			for track in self.log.tracks.lock().iter() {
+				let _ = VoiceTrack::stop(track);
+			}
+		}
+
+		fn add_global_event<F: EventHandler + 'static>(&mut self, _event: Event, _action: F) {}
+
+		fn remove_all_global_events(&mut self) {}
+	}
+}
@@ -0,0 +1,176 @@
+use crate::{
+	config::DuckRules,
+	constants::{SPEECH_HOLD_MS, VOICEHUNT_FRAME_TIME},
+};
+use serenity::async_trait;
+use songbird::events::{Event, EventContext, EventHandler};
+use std::{
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// Notes when anyone in the call last sent us voice.
+#[derive(Clone, Debug)]
+pub(super) struct SpeechWatch {
+	epoch: Instant,
+	/// Milliseconds since `epoch` (plus one) that voice was last heard; 0 if never.
+	heard: Arc<AtomicU64>,
+	/// Woken whenever someone starts talking after a lull.
+	started: Arc<Notify>,
+}
+
+impl SpeechWatch {
+	pub fn new() -> Self {
+		Self {
+			epoch: Instant::now(),
+			heard: Arc::new(AtomicU64::new(0)),
+			started: Arc::new(Notify::new()),
+		}
+	}
+
+	/// Waits for someone to start talking.
+	pub async fn started(&self) {
+		self.started.notified().await
+	}
+
+	/// When the current speaker will be counted as having stopped, if anyone's talking.
+	pub fn quiet_at(&self) -> Option<Instant> {
+		match self.heard.load(Ordering::Relaxed) {
+			0 => None,
+			heard => Some(self.epoch + Duration::from_millis(heard - 1 + SPEECH_HOLD_MS))
+				.filter(|t| *t > Instant::now()),
+		}
+	}
+
+	pub fn speaking(&self) -> bool {
+		match self.heard.load(Ordering::Relaxed) {
+			0 => false,
+			heard => self.now().saturating_sub(heard) < SPEECH_HOLD_MS,
+		}
+	}
+
+	/// How long since anyone last spoke, or since we started listening.
+	pub fn silent_for(&self) -> Duration {
+		match self.heard.load(Ordering::Relaxed) {
+			0 => self.epoch.elapsed(),
+			heard => Duration::from_millis(self.now().saturating_sub(heard)),
+		}
+	}
+
+	fn now(&self) -> u64 {
+		self.epoch.elapsed().as_millis() as u64 + 1
+	}
+}
+
+#[async_trait]
+impl EventHandler for SpeechWatch {
+	async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+		if let EventContext::VoiceTick(tick) = ctx {
+			if !tick.speaking.is_empty() {
+				let lull = !self.speaking();
+				self.heard.store(self.now(), Ordering::Relaxed);
+
+				if lull {
+					self.started.notify_one();
+				}
+			}
+		}
+
+		None
+	}
+}
+
+/// Felyne's volume scaling while people are talking over her.
+pub(super) struct Ducker {
+	rules: DuckRules,
+	level: f32,
+	target: f32,
+	last: Instant,
+}
+
+impl Ducker {
+	pub fn new(rules: DuckRules) -> Self {
+		Self {
+			rules,
+			level: 1.0,
+			target: 1.0,
+			last: Instant::now(),
+		}
+	}
+
+	pub fn set_rules(&mut self, rules: DuckRules) {
+		self.rules = rules;
+	}
+
+	pub fn level(&self) -> f32 {
+		self.level
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.rules.enabled
+	}
+
+	/// Whether we're still part-way between levels, and need stepping each frame.
+	pub fn ramping(&self) -> bool {
+		self.level != self.target
+	}
+
+	/// Whether new sound effects should wait for a lull.
+	pub fn holds_sfx(&self, speaking: bool) -> bool {
+		self.rules.enabled && self.rules.hold_sfx && speaking
+	}
+
+	/// Moves towards the ducked (or full) level, returning the gain to apply.
+	pub fn step(&mut self, speaking: bool) -> f32 {
+		// We're only stepped every frame while ramping, so a longer gap
+		// means speech has only just started or stopped.
+		let now = Instant::now();
+		let elapsed = (now - self.last).min(Duration::from_millis(VOICEHUNT_FRAME_TIME));
+		self.last = now;
+
+		let depth = self.rules.depth.clamp(0.0, 1.0);
+		let (target, over) = if self.rules.enabled && speaking {
+			(depth, self.rules.attack_ms)
+		} else {
+			(1.0, self.rules.release_ms)
+		};
+
+		self.target = target;
+		self.level = approach(self.level, target, 1.0 - depth, elapsed, over);
+		self.level
+	}
+}
+
+/// Moves `level` towards `target`, covering `span` every `over_ms`.
+fn approach(level: f32, target: f32, span: f32, elapsed: Duration, over_ms: u64) -> f32 {
+	if over_ms == 0 || span <= 0.0 {
+		return target;
+	}
+
+	let step = span * elapsed.as_secs_f32() / Duration::from_millis(over_ms).as_secs_f32();
+
+	if level > target {
+		(level - step).max(target)
+	} else {
+		(level + step).min(target)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_approach_ramps_and_clamps() {
+		let half = Duration::from_millis(500);
+
+		assert_eq!(approach(1.0, 0.5, 0.5, half, 1_000), 0.75);
+		assert_eq!(approach(0.5, 1.0, 0.5, half, 1_000), 0.75);
+		assert_eq!(approach(0.6, 0.5, 0.5, half, 1_000), 0.5);
+		assert_eq!(approach(1.0, 0.5, 0.5, half, 0), 0.5);
+	}
+}
@@ -0,0 +1,68 @@
+use super::driver::VoiceTrack;
+use std::time::{Duration, Instant};
+
+/// A volume ramp on a playing track, relative to Felyne's overall volume.
+pub(super) struct Fade<T> {
+	track: T,
+	from: f32,
+	to: f32,
+	start: Instant,
+	over: Duration,
+}
+
+impl<T: VoiceTrack> Fade<T> {
+	pub fn new(track: T, from: f32, to: f32, over: Duration) -> Self {
+		Self {
+			track,
+			from,
+			to,
+			start: Instant::now(),
+			over,
+		}
+	}
+
+	/// Moves the ramp along, returning `false` once it's finished.
+	/// Tracks which have faded out are stopped.
+	pub fn step(&self, vol: f32) -> bool {
+		let progress = progress(self.start.elapsed(), self.over);
+		let _ = self
+			.track
+			.set_volume((self.from + (self.to - self.from) * progress) * vol);
+
+		if progress < 1.0 {
+			true
+		} else {
+			if self.to == 0.0 {
+				let _ = self.track.stop();
+			}
+			false
+		}
+	}
+
+	pub fn stop(self) {
+		let _ = self.track.stop();
+	}
+}
+
+fn progress(elapsed: Duration, over: Duration) -> f32 {
+	if over.is_zero() {
+		1.0
+	} else {
+		(elapsed.as_secs_f32() / over.as_secs_f32()).min(1.0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_progress_clamps() {
+		let over = Duration::from_secs(2);
+
+		assert_eq!(progress(Duration::ZERO, over), 0.0);
+		assert_eq!(progress(Duration::from_secs(1), over), 0.5);
+		assert_eq!(progress(Duration::from_secs(5), over), 1.0);
+		assert_eq!(progress(Duration::from_secs(1), Duration::ZERO), 1.0);
+	}
+}
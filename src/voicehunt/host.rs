@@ -0,0 +1,251 @@
+use super::{
+	driver::VoiceDriver,
+	guild_soundscape,
+	receiver::{listen_in, ReceiverSignal},
+	voicehunt_rejoin,
+};
+use crate::{
+	config::{DuckRules, PlaybackPrefs},
+	constants::*,
+	guild::GuildState,
+	soundscape::Soundscape,
+	trace_sink::{TraceSinkKey, WriteGuard},
+	user::UserState,
+};
+use flume::Sender;
+use serenity::{
+	async_trait,
+	client::Context,
+	model::prelude::{ChannelId, GuildId},
+	prelude::RwLock,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::warn;
+
+/// Everything `felyne_life` needs from the rest of the bot: its server's
+/// settings, and somewhere to post to.
+#[async_trait]
+pub trait HuntHost: Send + Sync + 'static {
+	/// The server's chosen soundscape pack.
+	async fn soundscape(&self) -> Arc<Soundscape>;
+
+	async fn mixing(&self) -> (DuckRules, PlaybackPrefs);
+
+	/// Sounds uploaded by this server.
+	async fn custom_sfx(&self) -> Vec<String>;
+
+	/// Starts gathering voice traces in `channel`, if the server allows it.
+	async fn listen_in<D: VoiceDriver>(
+		&self,
+		driver: &mut D,
+		channel: ChannelId,
+		making_noise: bool,
+		chan_users: usize,
+	) -> Option<Sender<ReceiverSignal>>;
+
+	/// Registers a trace write which `TraceStore::flush` should wait for, if
+	/// traces are being stored at all.
+	async fn begin_write(&self) -> Option<WriteGuard>;
+
+	/// Posts `text` to a text channel without holding up playback.
+	fn announce(&self, channel: ChannelId, text: String);
+
+	/// Tells the hunt that Felyne couldn't get back into her channel, so
+	/// that she's sent wherever she now ought to be.
+	fn lost_channel(&self);
+
+	/// How to space out attempts to get back into a dropped channel.
+	fn backoff(&self) -> Backoff {
+		Backoff::default()
+	}
+}
+
+/// Exponential backoff between a fixed number of attempts.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+	pub first: Duration,
+	pub max: Duration,
+	pub attempts: u32,
+}
+
+impl Default for Backoff {
+	fn default() -> Self {
+		Self {
+			first: Duration::from_millis(VOICEHUNT_REJOIN_MS),
+			max: Duration::from_millis(VOICEHUNT_REJOIN_MAX_MS),
+			attempts: VOICEHUNT_REJOIN_ATTEMPTS,
+		}
+	}
+}
+
+impl Backoff {
+	/// How long to wait before the `attempt`th try, or `None` once out of tries.
+	pub fn delay(&self, attempt: u32) -> Option<Duration> {
+		if attempt < self.attempts {
+			let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+			Some(self.first.saturating_mul(factor).min(self.max))
+		} else {
+			None
+		}
+	}
+}
+
+pub struct GuildHost {
+	pub guild_id: GuildId,
+	pub guild_state: Arc<RwLock<GuildState>>,
+	pub user_states: Arc<UserState>,
+	pub ctx: Context,
+}
+
+#[async_trait]
+impl HuntHost for GuildHost {
+	async fn soundscape(&self) -> Arc<Soundscape> {
+		guild_soundscape(&self.ctx, &self.guild_state).await
+	}
+
+	async fn mixing(&self) -> (DuckRules, PlaybackPrefs) {
+		let lock = self.guild_state.read().await;
+		(lock.duck_rules(), lock.playback())
+	}
+
+	async fn custom_sfx(&self) -> Vec<String> {
+		let lock = self.guild_state.read().await;
+		lock.custom_sfx().iter().map(|s| s.file.clone()).collect()
+	}
+
+	async fn listen_in<D: VoiceDriver>(
+		&self,
+		driver: &mut D,
+		channel: ChannelId,
+		making_noise: bool,
+		chan_users: usize,
+	) -> Option<Sender<ReceiverSignal>> {
+		let (opt_in, gather_mode) = {
+			let lock = self.guild_state.read().await;
+			(lock.server_opt(), lock.gather())
+		};
+
+		listen_in(
+			driver,
+			opt_in,
+			gather_mode,
+			self.user_states.clone(),
+			self.guild_state.clone(),
+			self.guild_id,
+			channel,
+			making_noise,
+			chan_users,
+			self.ctx.clone(),
+		)
+		.await
+	}
+
+	async fn begin_write(&self) -> Option<WriteGuard> {
+		let data = self.ctx.data.read().await;
+		data.get::<TraceSinkKey>().map(|store| store.begin_write())
+	}
+
+	fn announce(&self, channel: ChannelId, text: String) {
+		let http = self.ctx.http.clone();
+
+		tokio::spawn(async move {
+			if let Err(e) = channel.say(&http, text).await {
+				warn!("[VoiceHunt] Couldn't post to {:?}: {:?}", channel, e);
+			}
+		});
+	}
+
+	fn lost_channel(&self) {
+		let ctx = self.ctx.clone();
+		let guild_id = self.guild_id;
+
+		tokio::spawn(async move {
+			voicehunt_rejoin(&ctx, guild_id).await;
+		});
+	}
+}
+
+#[cfg(test)]
+pub mod fake {
+	use super::*;
+	use crate::trace_sink::TraceStore;
+	use parking_lot::Mutex;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	/// A server with default settings, which keeps anything said to it.
+	pub struct FakeHost {
+		pub soundscape: Arc<Soundscape>,
+		pub said: Arc<Mutex<Vec<(ChannelId, String)>>>,
+		/// How many times Felyne has given up on her channel.
+		pub lost: Arc<AtomicUsize>,
+		/// Stands in for a trace capture in every channel she joins.
+		pub capture: Sender<ReceiverSignal>,
+		pub store: Arc<TraceStore>,
+	}
+
+	#[async_trait]
+	impl HuntHost for FakeHost {
+		async fn soundscape(&self) -> Arc<Soundscape> {
+			self.soundscape.clone()
+		}
+
+		async fn mixing(&self) -> (DuckRules, PlaybackPrefs) {
+			Default::default()
+		}
+
+		async fn custom_sfx(&self) -> Vec<String> {
+			vec![]
+		}
+
+		async fn listen_in<D: VoiceDriver>(
+			&self,
+			_driver: &mut D,
+			_channel: ChannelId,
+			_making_noise: bool,
+			_chan_users: usize,
+		) -> Option<Sender<ReceiverSignal>> {
+			Some(self.capture.clone())
+		}
+
+		async fn begin_write(&self) -> Option<WriteGuard> {
+			Some(self.store.begin_write())
+		}
+
+		fn announce(&self, channel: ChannelId, text: String) {
+			self.said.lock().push((channel, text));
+		}
+
+		fn lost_channel(&self) {
+			self.lost.fetch_add(1, Ordering::Relaxed);
+		}
+
+		/// Quick enough that tests needn't wait around.
+		fn backoff(&self) -> Backoff {
+			Backoff {
+				first: Duration::from_millis(5),
+				max: Duration::from_millis(20),
+				attempts: 3,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_backoff_doubles_up_to_max() {
+		let backoff = Backoff {
+			first: Duration::from_secs(1),
+			max: Duration::from_secs(5),
+			attempts: 4,
+		};
+
+		assert_eq!(backoff.delay(0), Some(Duration::from_secs(1)));
+		assert_eq!(backoff.delay(1), Some(Duration::from_secs(2)));
+		assert_eq!(backoff.delay(2), Some(Duration::from_secs(4)));
+		assert_eq!(backoff.delay(3), Some(Duration::from_secs(5)));
+		assert_eq!(backoff.delay(4), None);
+	}
+}
@@ -329,12 +329,13 @@ impl LiveTrace {
 		self.push_event(time, ssrc, evt);
 	}
 
+	/// Anonymises this trace, also returning the users whose data it contains.
 	pub async fn convert_to_stored(
 		&mut self,
 		user_data: Arc<UserState>,
 		guild_state: Arc<RwLock<GuildState>>,
 		ctx: &Context,
-	) -> FelyneTrace {
+	) -> (FelyneTrace, Vec<UserId>) {
 		let final_time = Instant::now();
 
 		let length = final_time
@@ -388,7 +389,14 @@ impl LiveTrace {
 			.filter_map(|user_id| user_id_to_opaque.get(user_id).copied())
 			.collect();
 
-		FelyneTrace::Vers2(FelyneTraceV2 {
+		let contributors = self
+			.user_to_ssrcs
+			.keys()
+			.filter(|u_id| !users_to_exclude.contains(u_id) && Some(**u_id) != self.my_uid)
+			.copied()
+			.collect();
+
+		let trace = FelyneTrace::Vers2(FelyneTraceV2 {
 			events,
 			length,
 			label: self.label.into(),
@@ -398,7 +406,9 @@ impl LiveTrace {
 			optout_users,
 			total_user_count,
 			starting_user_count: self.users_at_start,
-		})
+		});
+
+		(trace, contributors)
 	}
 
 	pub fn unify_event_streams(
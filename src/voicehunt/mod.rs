@@ -1,44 +1,98 @@
+pub mod driver;
+mod duck;
+mod fade;
+mod host;
 pub mod live;
 pub mod mode;
+pub mod quest;
 pub mod receiver;
-
-use crate::{automata::*, constants::*, guild::*, user::*, Resources, RxMap};
+pub mod status;
+#[cfg(test)]
+mod test;
+
+use crate::{
+	config::{
+		ChannelFilter,
+		DuckRules,
+		IdleRules,
+		PlaybackPrefs,
+		PopulationRules,
+		ReactionRules,
+		SwitchRules,
+	},
+	constants::*,
+	guild::*,
+	soundscape::*,
+	user::*,
+	Resources,
+	RxMap,
+};
 use dashmap::{mapref::entry::Entry as DashEntry, DashMap};
-use flume::{self, Receiver, Sender, TryRecvError};
+use driver::{VoiceDriver, VoiceTrack};
+use duck::{Ducker, SpeechWatch};
+use fade::Fade;
+use flume::{self, Receiver, Sender, WeakSender};
+use host::{GuildHost, HuntHost};
+use quest::{Quest, QuestCommand, QuestEvent};
 use rand::{distributions::*, thread_rng};
-use receiver::{listen_in, ReceiverSignal};
+use receiver::ReceiverSignal;
 use serenity::{async_trait, client::*, model::prelude::*, prelude::*};
 use songbird::{
-	events::{Event, EventContext, EventHandler, TrackEvent},
+	events::{
+		context_data::DisconnectKind,
+		CoreEvent,
+		Event,
+		EventContext,
+		EventHandler,
+		TrackEvent,
+	},
 	serenity::SongbirdKey,
-	tracks::TrackHandle,
 	Call,
 };
+use status::*;
 use std::{
-	collections::hash_map::{Entry, HashMap},
-	sync::Arc,
-	time::Duration,
+	collections::{HashMap, HashSet, VecDeque},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
 };
 use tokio::time;
 use tracing::*;
 
-struct TrackErrorNotifier;
+/// Tells `felyne_life` when its voice connection drops or comes back.
+#[derive(Clone)]
+struct DriverWatch(Arc<WeakSender<VoiceHuntMessage>>);
 
 #[async_trait]
-impl EventHandler for TrackErrorNotifier {
-    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-        if let EventContext::Track(track_list) = ctx {
-            for (state, handle) in *track_list {
-                println!(
-                    "Track {:?} encountered an error: {:?}",
-                    handle.uuid(),
-                    state.playing
-                );
-            }
-        }
-
-        None
-    }
+impl EventHandler for DriverWatch {
+	async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+		let msg = match ctx {
+			// Without a reason, Felyne asked to leave (or move). Failed joins are
+			// already seen by whoever asked to join.
+			EventContext::DriverDisconnect(data)
+				if data.reason.is_some() && data.kind != DisconnectKind::Connect =>
+			{
+				warn!(
+					"[VoiceHunt] {:?} disconnected: {:?} ({:?}).",
+					data.guild_id, data.kind, data.reason
+				);
+				VoiceHuntMessage::Disconnected
+			},
+			EventContext::DriverConnect(_) | EventContext::DriverReconnect(_) =>
+				VoiceHuntMessage::Reconnected,
+			_ => return None,
+		};
+
+		match self.0.upgrade() {
+			Some(tx) => {
+				let _ = tx.send(msg);
+				None
+			},
+			None => Some(Event::Cancel),
+		}
+	}
 }
 
 pub struct VoiceHunt;
@@ -53,17 +107,37 @@ pub enum VoiceHuntCommand {
 	BraveHunt,
 	Stalk,
 	DirectedHunt(ChannelId),
+	Follow(UserId),
 	Volume(f32),
+	Sfx(String, VolumeRange),
+	QueueBgm(String, VolumeRange),
+	Skip,
+	Duck(DuckRules),
+	Mix(PlaybackPrefs),
+	Quest(QuestCommand),
 }
 
 #[derive(Debug)]
 enum VoiceHuntMessage {
-	Channel(ChannelId, bool, usize),
+	Channel(ChannelId, usize),
 	Stealth,
 	Unstealth,
 	NoChannel,
 	Volume(f32),
 	Cart,
+	Sleep,
+	/// The voice connection dropped without Felyne asking to leave.
+	Disconnected,
+	/// The voice connection came back by itself.
+	Reconnected,
+	Sfx(String, VolumeRange),
+	QueueBgm(String, VolumeRange),
+	Skip,
+	Status(Sender<HuntStatus>),
+	React(Movement),
+	Duck(DuckRules),
+	Mix(PlaybackPrefs),
+	Quest(QuestCommand),
 }
 
 #[derive(Debug)]
@@ -74,6 +148,20 @@ enum VoiceHuntResponse {
 #[derive(Debug)]
 struct Incumbent(u64, ChannelId);
 
+/// Per-guild rules on which channels autonomous hunts may pick, whose
+/// presence counts towards choosing them, how Felyne greets arrivals, and
+/// when she gives up on a quiet channel.
+#[derive(Debug, Default)]
+struct HuntRules {
+	filter: ChannelFilter,
+	avoided: HashSet<ChannelId>,
+	population: PopulationRules,
+	switching: SwitchRules,
+	reactions: ReactionRules,
+	idle: IdleRules,
+	bots: HashSet<UserId>,
+}
+
 #[derive(Debug)]
 pub struct VHState {
 	guild_id: GuildId,
@@ -86,6 +174,25 @@ pub struct VHState {
 
 	active_channel: Option<ChannelId>,
 	incumbent_channel: Option<Incumbent>,
+	rules: HuntRules,
+
+	/// The channel Felyne was last sent to, and when.
+	settled: Option<(ChannelId, Instant)>,
+	/// A busier channel waiting out `SwitchRules::debounce_secs`, and since when.
+	challenger: Option<(ChannelId, Instant)>,
+	recheck_at: Option<Instant>,
+	last_reaction: Option<Instant>,
+
+	/// Who's talking in Felyne's channel, shared with `felyne_life`.
+	speech: SpeechWatch,
+	/// Whether Felyne left her channel for being idle, and is waiting for
+	/// someone to turn up.
+	asleep: bool,
+	/// Since when nobody in Felyne's channel has been able to hear her.
+	unheard_since: Option<Instant>,
+	idle_check_at: Option<Instant>,
+
+	ctx: Context,
 
 	huntsim_tx: Option<Sender<VoiceHuntMessage>>,
 	huntsim_rx: Option<Receiver<VoiceHuntResponse>>,
@@ -101,6 +208,11 @@ impl VHState {
 		guild_state: &Arc<RwLock<GuildState>>,
 		user_states: &Arc<UserState>,
 	) -> Self {
+		let (base_state, volume) = {
+			let gs = guild_state.read().await;
+			(gs.join(), gs.playback().volume)
+		};
+
 		// NOTE: will need some further changes if I want to start
 		// in the Stalk or BraveHunt states...
 		let mut out = VHState {
@@ -110,20 +222,28 @@ impl VHState {
 			population_counts: HashMap::new(),
 
 			join_mode: VoiceHuntCommand::Carted,
-			volume: 1.0,
+			volume,
 
 			active_channel: None,
 			incumbent_channel: None,
+			rules: Default::default(),
+
+			settled: None,
+			challenger: None,
+			recheck_at: None,
+			last_reaction: None,
+
+			speech: SpeechWatch::new(),
+			asleep: false,
+			unheard_since: None,
+			idle_check_at: None,
+
+			ctx: ctx.clone(),
 
 			huntsim_tx: None,
 			huntsim_rx: None,
 		};
 
-		let base_state = {
-			let gs = guild_state.read().await;
-			gs.join()
-		};
-
 		out.control(
 			vox_manager,
 			base_state.as_command(),
@@ -147,10 +267,16 @@ impl VHState {
 	) {
 		let (sender, receiver) = flume::unbounded();
 		let (reverse_sender, reverse_receiver) = flume::unbounded();
+		let watch_sender = sender.downgrade();
 		let guild_id = self.guild_id;
 		let vol = self.volume;
-
-		let self_tx = sender.clone();
+		let speech = self.speech.clone();
+		let host = GuildHost {
+			guild_id,
+			guild_state,
+			user_states,
+			ctx,
+		};
 
 		// Begin!
 		tokio::spawn(async move {
@@ -158,14 +284,13 @@ impl VHState {
 			felyne_life(
 				receiver,
 				reverse_sender,
+				watch_sender,
 				vox_manager,
 				guild_id,
 				vol,
-				self_tx,
+				speech,
 				resources,
-				guild_state,
-				user_states,
-				ctx,
+				host,
 			)
 			.await;
 		});
@@ -189,7 +314,7 @@ impl VHState {
 		if let Carted = self.join_mode {
 			match mode {
 				Carted => {},
-				Volume(_) => {},
+				Volume(_) | Sfx(..) | QueueBgm(..) | Skip | Duck(_) | Mix(_) | Quest(_) => {},
 				_ => {
 					// Moving from Carted to active mode.
 					// Spawn thread.
@@ -215,6 +340,8 @@ impl VHState {
 				self.huntsim_tx = None;
 				self.huntsim_rx = None;
 				self.active_channel = None;
+				self.settled = None;
+				self.asleep = false;
 				self.join_mode = mode;
 				false
 			},
@@ -240,20 +367,92 @@ impl VHState {
 				self.send(VoiceHuntMessage::Stealth);
 				true
 			},
+			Follow(_) => {
+				// Channel is chosen by wherever our target is.
+				self.active_channel = None;
+				self.join_mode = mode;
+				self.send(VoiceHuntMessage::Unstealth);
+				true
+			},
 			Volume(vol) => {
 				self.send(VoiceHuntMessage::Volume(vol));
 				self.volume = vol;
 				false
 			},
+			Sfx(file, vol) => {
+				self.send(VoiceHuntMessage::Sfx(file, vol));
+				false
+			},
+			QueueBgm(file, vol) => {
+				self.send(VoiceHuntMessage::QueueBgm(file, vol));
+				false
+			},
+			Skip => {
+				self.send(VoiceHuntMessage::Skip);
+				false
+			},
+			Duck(rules) => {
+				self.send(VoiceHuntMessage::Duck(rules));
+				false
+			},
+			Mix(prefs) => {
+				self.send(VoiceHuntMessage::Mix(prefs));
+				self.volume = prefs.volume;
+				false
+			},
+			Quest(cmd) => {
+				self.send(VoiceHuntMessage::Quest(cmd));
+				false
+			},
 		};
 
 		if chan_change {
+			// Being sent somewhere wakes Felyne, wherever that is.
+			self.asleep = false;
 			self.update_channel();
 		} // Now set the channel.
 
 		self
 	}
 
+	/// Sends Felyne home without changing the guild's saved join mode,
+	/// waiting up to `timeout` for her outro to finish.
+	async fn shutdown(&mut self, timeout: Duration) {
+		if let VoiceHuntCommand::Carted = self.join_mode {
+			return;
+		}
+
+		self.send(VoiceHuntMessage::Cart);
+
+		if let Some(rx) = self.huntsim_rx.as_ref() {
+			if time::timeout(timeout, rx.recv_async()).await.is_err() {
+				warn!(
+					"[VoiceHunt] {:?} didn't finish its outro before shutdown.",
+					self.guild_id
+				);
+				return;
+			}
+		}
+
+		self.huntsim_tx = None;
+		self.huntsim_rx = None;
+		self.active_channel = None;
+		self.settled = None;
+		self.asleep = false;
+		self.join_mode = VoiceHuntCommand::Carted;
+	}
+
+	/// Asks `felyne_life` what it's up to, returning where its answer will arrive.
+	fn query(&mut self) -> Option<Receiver<HuntStatus>> {
+		let (tx, rx) = flume::bounded(1);
+
+		self.huntsim_tx
+			.as_ref()?
+			.send(VoiceHuntMessage::Status(tx))
+			.ok()
+			.map(|_| rx)
+	}
+
 	fn send(&mut self, msg: VoiceHuntMessage) {
 		if let Some(tx) = self.huntsim_tx.as_ref() {
 			if let Err(e) = tx.send(msg) {
@@ -264,477 +463,773 @@ impl VHState {
 
 	fn register_user_states(&mut self, voice_states: HashMap<UserId, VoiceState>) -> &mut Self {
 		self.user_states = voice_states;
+		self.user_states.remove(&self.user_id);
 
-		let mut scan_incumbent = false;
-
-		for vox in self.user_states.clone().values() {
-			scan_incumbent |= self.register_user_state(vox, false);
-		}
-
-		if scan_incumbent {
-			self.recalc_incumbent();
-		}
+		self.recount();
+		self.recalc_incumbent();
 		self.update_channel();
 
 		self
 	}
 
-	fn register_user_state(&mut self, state: &VoiceState, do_update: bool) -> bool {
+	fn register_user_state(&mut self, state: &VoiceState, do_update: bool) {
 		if state.user_id == self.user_id {
-			return false;
+			return;
 		}
 
-		let mut scan_incumbent = false;
-		if let Entry::Occupied(prior_state) = self.user_states.clone().entry(state.user_id) {
-			if let Some(channel) = prior_state.get().channel_id {
-				let count = {
-					let v = self.population_counts.entry(channel).or_insert(1);
-					*v = *v.max(&mut 1) - 1;
-					*v
-				};
+		let prior = self
+			.user_states
+			.insert(state.user_id, state.clone())
+			.and_then(|prior| prior.channel_id);
 
-				// If we lower the incumbent, we need to do a rescan.
-				scan_incumbent |= self.update_incumbent(count, channel);
-			}
+		if do_update {
+			self.react(state, prior);
 		}
 
-		if let Some(channel) = state.channel_id {
-			let count = {
-				let v = self.population_counts.entry(channel).or_insert(0);
-				*v += 1;
-				*v
-			};
-
-			self.update_incumbent(count, channel);
+		if do_update && self.asleep && self.rouses(state) {
+			info!(
+				"[VoiceHunt] {:?} woken by {:?}.",
+				self.guild_id, state.user_id
+			);
+			self.asleep = false;
 		}
 
-		self.user_states.insert(state.user_id, state.clone());
-
 		if do_update {
+			self.recount();
 			self.recalc_incumbent();
 			self.update_channel();
-			return false;
 		}
+	}
+
+	/// Updates the guild's channel and population rules.
+	///
+	/// Bots are remembered across calls, as we're only told about users
+	/// who've just changed state.
+	fn set_rules(&mut self, rules: HuntRules) -> &mut Self {
+		let mut bots = std::mem::take(&mut self.rules.bots);
+		bots.extend(rules.bots.iter().copied());
 
-		scan_incumbent
+		self.rules = HuntRules { bots, ..rules };
+
+		self
 	}
 
-	fn recalc_incumbent(&mut self) {
-		for (chan, count) in self.population_counts.clone().iter() {
-			self.update_incumbent(*count, *chan);
+	/// Plays a join or leave cue if `state` moved into or out of our channel.
+	fn react(&mut self, state: &VoiceState, prior: Option<ChannelId>) {
+		let here = match self.settled {
+			Some((chan, _)) if prior != state.channel_id => chan,
+			_ => return,
+		};
+
+		let rules = self.rules.reactions;
+		let movement = if state.channel_id == Some(here) && rules.on_join {
+			Movement::Join
+		} else if prior == Some(here) && rules.on_leave {
+			Movement::Leave
+		} else {
+			return;
+		};
+
+		let bot = self.rules.is_bot(state);
+		let now = Instant::now();
+		let cooling = self
+			.last_reaction
+			.map(|t| {
+				t.checked_add(Duration::from_secs(rules.cooldown_secs))
+					.is_none_or(|until| now < until)
+			})
+			.unwrap_or(false);
+
+		if !bot && !cooling {
+			self.last_reaction = Some(now);
+			self.send(VoiceHuntMessage::React(movement));
 		}
 	}
 
-	fn update_incumbent(&mut self, count: u64, channel: ChannelId) -> bool {
-		if let Some(Incumbent(count_old, chan_old)) = self.incumbent_channel {
-			if chan_old == channel || count > count_old {
-				self.incumbent_channel = if count == 0 {
-					None
-				} else {
-					Some(Incumbent(count, channel))
-				};
+	fn recount(&mut self) {
+		let rules = &self.rules;
+		let mut counts = HashMap::new();
 
-				count < count_old
-			} else {
-				false
+		for state in self.user_states.values() {
+			let bot = rules.is_bot(state);
+
+			if let Some(chan) = state
+				.channel_id
+				.filter(|_| rules.population.counts(state, bot))
+			{
+				*counts.entry(chan).or_insert(0) += 1;
 			}
-		} else {
-			self.incumbent_channel = if count == 0 {
-				None
-			} else {
-				Some(Incumbent(count, channel))
-			};
-			false
 		}
+
+		self.population_counts = counts;
+		self.update_unheard();
+	}
+
+	/// Whether `state` is someone up and about, who should wake a sleeping Felyne.
+	fn rouses(&self, state: &VoiceState) -> bool {
+		state.channel_id.is_some() && !(state.deaf || state.self_deaf) && !self.rules.is_bot(state)
+	}
+
+	/// Notes when Felyne's channel ran out of anyone who can hear her.
+	fn update_unheard(&mut self) {
+		let unheard = match self.settled {
+			Some((here, _)) => {
+				let rules = &self.rules;
+				self.user_states
+					.values()
+					.filter(|s| s.channel_id == Some(here) && !rules.is_bot(s))
+					.all(|s| s.deaf || s.self_deaf)
+			},
+			None => false,
+		};
+
+		self.unheard_since = match self.unheard_since {
+			Some(since) if unheard => Some(since),
+			_ if unheard => Some(Instant::now()),
+			_ => None,
+		};
+	}
+
+	/// Picks the busiest channel we're allowed in, sticking with the current
+	/// choice on a tie.
+	fn recalc_incumbent(&mut self) {
+		let current = self
+			.incumbent_channel
+			.as_ref()
+			.map(|Incumbent(_, chan)| *chan);
+		let rules = &self.rules;
+
+		self.incumbent_channel = self
+			.population_counts
+			.iter()
+			.filter(|(chan, count)| rules.eligible(**chan, **count))
+			.max_by_key(|(chan, count)| (**count, Some(**chan) == current))
+			.map(|(chan, count)| Incumbent(*count, *chan));
 	}
 
 	fn update_channel(&mut self) {
 		info!("{:?}", self.population_counts);
-		if let Some(chan) = self.active_channel {
+		if self.asleep {
+			return;
+		}
+
+		if let VoiceHuntCommand::Follow(user) = self.join_mode {
+			// Leave alongside them, and come back when they do.
+			match self.user_states.get(&user).and_then(|s| s.channel_id) {
+				Some(chan) => {
+					let chan_users = (*self.population_counts.get(&chan).unwrap_or(&0)) as usize;
+					self.send_channel(VoiceHuntMessage::Channel(chan, chan_users));
+				},
+				None => self.send_channel(VoiceHuntMessage::NoChannel),
+			}
+		} else if let Some(chan) = self.active_channel {
 			let chan_users = (*self.population_counts.get(&chan).unwrap_or(&0)) as usize;
-			self.send(VoiceHuntMessage::Channel(chan, true, chan_users));
+			self.send_channel(VoiceHuntMessage::Channel(chan, chan_users));
 		} else if let Some(Incumbent(chan_users, chan)) = self.incumbent_channel {
-			self.send(VoiceHuntMessage::Channel(chan, false, chan_users as usize));
+			if self.may_switch(chan, chan_users) {
+				self.send_channel(VoiceHuntMessage::Channel(chan, chan_users as usize));
+			}
 		} else {
-			self.send(VoiceHuntMessage::NoChannel);
+			self.send_channel(VoiceHuntMessage::NoChannel);
 		}
 	}
+
+	/// Whether an autonomous hunt should move to `chan` now, given the guild's
+	/// `SwitchRules`. If not yet, a recheck is scheduled for when it might.
+	fn may_switch(&mut self, chan: ChannelId, chan_users: u64) -> bool {
+		let now = Instant::now();
+		let rules = self.rules.switching;
+
+		let (curr, since) = match self.settled {
+			Some((curr, since)) if curr != chan => (curr, since),
+			_ => {
+				self.challenger = None;
+				return true;
+			},
+		};
+
+		// Only demand a margin while our current channel is still worth staying in.
+		let curr_users = *self.population_counts.get(&curr).unwrap_or(&0);
+		if self.rules.eligible(curr, curr_users)
+			&& chan_users < curr_users.saturating_add(rules.margin)
+		{
+			self.challenger = None;
+			return false;
+		}
+
+		let challenged = match self.challenger {
+			Some((challenger, t)) if challenger == chan => t,
+			_ => {
+				self.challenger = Some((chan, now));
+				now
+			},
+		};
+
+		// A wait too long to represent is one that never ends.
+		let ready_at = since
+			.checked_add(Duration::from_secs(rules.dwell_secs))
+			.zip(challenged.checked_add(Duration::from_secs(rules.debounce_secs)))
+			.map(|(dwelt, debounced)| dwelt.max(debounced));
+
+		match ready_at {
+			Some(t) if t <= now => {
+				self.challenger = None;
+				true
+			},
+			Some(t) => {
+				self.schedule_recheck(t);
+				false
+			},
+			None => false,
+		}
+	}
+
+	fn schedule_recheck(&mut self, at: Instant) {
+		if matches!(self.recheck_at, Some(t) if t <= at) {
+			return;
+		}
+
+		self.recheck_at = Some(at);
+
+		let ctx = self.ctx.clone();
+		let guild_id = self.guild_id;
+		tokio::spawn(async move {
+			time::sleep_until(at.into()).await;
+			voicehunt_recheck(&ctx, guild_id, at).await;
+		});
+	}
+
+	/// Sends Felyne to sleep if her channel has been idle for long enough, or
+	/// checks back when it might have been.
+	fn check_idle(&mut self) {
+		let (timeout, since) = match (self.rules.idle.timeout(), self.settled) {
+			(Some(timeout), Some((_, since))) if !self.asleep => (timeout, since),
+			_ => return,
+		};
+
+		let now = Instant::now();
+		let silent = self.speech.silent_for().min(now - since);
+		let unheard = self.unheard_since.map(|t| now - t).unwrap_or_default();
+		let idle = silent.max(unheard);
+
+		if idle >= timeout {
+			info!(
+				"[VoiceHunt] {:?} idle for {:?}: dozing off.",
+				self.guild_id, idle
+			);
+			self.asleep = true;
+			self.settled = None;
+			self.challenger = None;
+			self.unheard_since = None;
+			self.send(VoiceHuntMessage::Sleep);
+		} else if let Some(at) = now.checked_add(timeout - idle) {
+			self.schedule_idle_check(at);
+		}
+	}
+
+	fn schedule_idle_check(&mut self, at: Instant) {
+		if matches!(self.idle_check_at, Some(t) if t <= at) {
+			return;
+		}
+
+		self.idle_check_at = Some(at);
+
+		let ctx = self.ctx.clone();
+		let guild_id = self.guild_id;
+		tokio::spawn(async move {
+			time::sleep_until(at.into()).await;
+			voicehunt_idle_check(&ctx, guild_id, at).await;
+		});
+	}
+
+	fn send_channel(&mut self, msg: VoiceHuntMessage) {
+		self.settled = match msg {
+			VoiceHuntMessage::Channel(chan, ..) => match self.settled {
+				Some((curr, since)) if curr == chan => Some((curr, since)),
+				_ => Some((chan, Instant::now())),
+			},
+			_ => None,
+		};
+
+		self.send(msg);
+		self.update_unheard();
+		self.check_idle();
+	}
+}
+
+impl HuntRules {
+	fn is_bot(&self, state: &VoiceState) -> bool {
+		self.bots.contains(&state.user_id)
+			|| state.member.as_ref().map(|m| m.user.bot).unwrap_or(false)
+	}
+
+	fn eligible(&self, chan: ChannelId, count: u64) -> bool {
+		count >= self.population.min_users.max(1)
+			&& self.filter.permits(chan)
+			&& !self.avoided.contains(&chan)
+	}
 }
 
 #[inline]
-async fn quit_vox_channel(
-	manager_lock: Arc<Mutex<Call>>,
+async fn quit_vox_channel<D: VoiceDriver>(
+	manager_lock: Arc<Mutex<D>>,
 	_guild_id: GuildId,
 	receiver_chan: &mut Option<Sender<ReceiverSignal>>,
+	host: &impl HuntHost,
 ) {
 	let mut manager = manager_lock.lock().await;
 
 	manager.stop();
-	if let Some(chan) = receiver_chan {
-		let _ = chan.send(ReceiverSignal::Poison);
+	if let Some(chan) = receiver_chan.take() {
+		// The receiver is only dropped (and its trace saved) once songbird gets
+		// round to it, so register that write now: a flush can't then miss it.
+		let _ = chan.send(ReceiverSignal::Poison(host.begin_write().await));
 	}
-	*receiver_chan = None;
 
 	manager.remove_all_global_events();
 
-	let _ = manager.leave().await;
+	manager.leave().await;
 }
 
-#[inline]
-fn random_element<T>(arr: &[T]) -> &T {
-	let mut rng = thread_rng();
-	&arr[Uniform::new(0, arr.len()).sample(&mut rng)]
-}
+fn play_sound<'a, D: VoiceDriver>(
+	state: &'a SoundState,
+	extra: &'a [String],
+	vox: &mut D,
+	stealth: bool,
+	resources: &RxMap,
+	donezo: &Sender<(FelyneEvt, u128)>,
+	msg: FelyneEvt,
+) -> Option<(D::Track, &'a str)> {
+	if stealth {
+		return None;
+	}
+
+	// Steer clear of anything which has failed before, unless that's all there is.
+	let loaded = || {
+		state
+			.tracks
+			.iter()
+			.chain(extra)
+			.filter(|file| resources.contains_key(file.as_str()))
+	};
+	let mut choices: Vec<_> = loaded()
+		.filter(|file| {
+			resources
+				.get(file.as_str())
+				.map(|s| !s.is_bad())
+				.unwrap_or(false)
+		})
+		.collect();
+	if choices.is_empty() {
+		choices = loaded().collect();
+	}
+
+	if choices.is_empty() {
+		return None;
+	}
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum BgmClass {
-	NoBgm,
-	Intro,
-	Ambience,
-	Music,
-	Bonus,
-	BonusResult,
-	Outro,
+	let file = choices[Uniform::new(0, choices.len()).sample(&mut thread_rng())];
+
+	play_file(file, vox, resources, donezo, msg).map(|track| (track, file.as_str()))
 }
 
-impl BgmClass {
-	fn no_gargwa(self) -> bool {
-		use BgmClass::*;
+fn play_file<D: VoiceDriver>(
+	file: &str,
+	vox: &mut D,
+	resources: &RxMap,
+	donezo: &Sender<(FelyneEvt, u128)>,
+	msg: FelyneEvt,
+) -> Option<D::Track> {
+	let chan = donezo.clone();
 
-		self == Music || self == Bonus || self == BonusResult
-	}
+	resources
+		.get(file)
+		.map(|guard| vox.play(file, guard.value()))
+		.inspect(move |track| {
+			let id = track.id();
+			let _ = track.add_event(
+				Event::Track(TrackEvent::End),
+				FelyneEndTrack { chan, msg, id },
+			);
+			watch_errors(track, file, donezo);
+		})
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum BgmInput {
-	TryIntro,
-	Advance,
-	MoveOutro,
+/// Tells `felyne_life` if `track` fails, as it won't then reach its end.
+fn watch_errors<T: VoiceTrack>(track: &T, file: &str, donezo: &Sender<(FelyneEvt, u128)>) {
+	let _ = track.add_event(
+		Event::Track(TrackEvent::Error),
+		FelyneEndTrack {
+			chan: donezo.clone(),
+			msg: FelyneEvt::Failed(file.to_string()),
+			id: track.id(),
+		},
+	);
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum SfxClass {
-	NoSfx,
-	Cat,
-	Bonus,
+/// Notes that `file` failed to play, quarantining it from `resources` until
+/// they're next loaded if it keeps failing.
+fn mark_failed(resources: &RxMap, file: &str, guild_id: GuildId) {
+	let failures = resources
+		.get(file)
+		.map(|sound| sound.record_failure())
+		.unwrap_or(0);
+
+	warn!(
+		"[VoiceHunt] {:?} couldn't play {} (failure {}).",
+		guild_id, file, failures
+	);
+
+	if failures >= TRACK_QUARANTINE_FAILURES {
+		resources.remove(file);
+		error!(
+			"[VoiceHunt] Quarantined {} after {} failures.",
+			file, failures
+		);
+	}
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum SfxInput {
-	Advance,
+/// Rescales playing tracks from one overall volume to another.
+fn rescale<T: VoiceTrack>(tracks: &[&Option<T>], from: f32, to: f32) {
+	if from <= 0.0 {
+		return;
+	}
+
+	for track in tracks.iter().filter_map(|t| t.as_ref()) {
+		let _ = track.scale_volume(to / from);
+	}
 }
 
-fn play_bgm(
-	state: BgmClass,
-	vox: &mut Call,
-	stealth: bool,
+/// Plays a single track from `state` over whatever's going, outside of either machine.
+fn play_cue<D: VoiceDriver>(
+	state: &SoundState,
+	vox: &mut D,
 	resources: &RxMap,
-	donezo: &Sender<FelyneEvt>,
-) -> Option<TrackHandle> {
-	use BgmClass::*;
+	donezo: &Sender<(FelyneEvt, u128)>,
+	gain: f32,
+) {
+	if let Some(file) = state.draw_track() {
+		if let Some(guard) = resources.get(file) {
+			let track = vox.play(file, guard.value());
+			let _ = track.set_volume(state.volume.draw() * gain);
+			watch_errors(&track, file, donezo);
+		}
+	}
+}
 
-	if stealth {
-		return None;
+/// Sounds uploaded by this server, if `state` mixes them in.
+async fn custom_sounds(state: &SoundState, host: &impl HuntHost) -> Vec<String> {
+	if state.custom {
+		host.custom_sfx().await
+	} else {
+		vec![]
 	}
+}
 
-	let el_list = match state {
-		NoBgm => {
-			return None;
-		},
-		Intro => START,
-		Ambience => AMBIENCE,
-		Music => BGM,
-		Bonus => BBQ,
-		BonusResult => BBQ_RESULT,
-		Outro => SLEEP,
-	};
+/// Loops a BGM track between its loop points, and (if crossfading) asks for
+/// the next track a little before this one will end.
+fn prepare_bgm<T: VoiceTrack>(
+	track: &T,
+	file: &str,
+	soundscape: &Soundscape,
+	resources: &RxMap,
+	donezo: &Sender<(FelyneEvt, u128)>,
+) {
+	let points = soundscape.bgm.loop_points(file).copied();
+
+	if let Some(points) = points {
+		let _ = track.add_event(
+			Event::Periodic(points.end - points.start, Some(points.end)),
+			FelyneLoop {
+				track: track.clone(),
+				start: points.start,
+				remaining: AtomicUsize::new(points.times),
+			},
+		);
+	}
 
-	let chan = donezo.clone();
+	let length = resources
+		.get(file)
+		.and_then(|sound| sound.duration())
+		.map(|d| d + points.map(|p| p.extra()).unwrap_or_default());
 
-	resources
-		.get(random_element(el_list))
-		.map(|guard| vox.play(guard.value().into()))
-		.map(move |track| {
+	if let (Some(fade), Some(length)) = (soundscape.crossfade, length) {
+		if length > fade * 2 {
+			let chan = donezo.clone();
 			let _ = track.add_event(
-				Event::Track(TrackEvent::End),
+				Event::Delayed(length - fade),
 				FelyneEndTrack {
 					chan,
-					msg: FelyneEvt::BgmEnd,
+					msg: FelyneEvt::BgmFade,
+					id: track.id(),
 				},
 			);
-			track
-		})
+		}
+	}
 }
 
+/// Sets a new BGM track's volume, ramping up from silence if crossfading.
+fn ramp_in<T: VoiceTrack>(
+	track: &T,
+	gain: f32,
+	vol: f32,
+	fade_in: Option<Duration>,
+	fades: &mut Vec<Fade<T>>,
+) {
+	match fade_in {
+		Some(over) => {
+			let _ = track.set_volume(0.0);
+			fades.push(Fade::new(track.clone(), 0.0, gain, over));
+		},
+		None => {
+			let _ = track.set_volume(gain * vol);
+		},
+	}
+}
+
+fn is_current<T: VoiceTrack>(curr: &Option<T>, id: u128) -> bool {
+	curr.as_ref().map(|t| t.id() == id).unwrap_or(false)
+}
+
+/// Tells `felyne_life` that track `id` has reached some point.
 struct FelyneEndTrack {
-	chan: Sender<FelyneEvt>,
+	chan: Sender<(FelyneEvt, u128)>,
 	msg: FelyneEvt,
+	id: u128,
 }
 
 #[async_trait]
 impl EventHandler for FelyneEndTrack {
 	async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
-		let _ = self.chan.send(self.msg);
+		let _ = self.chan.send((self.msg.clone(), self.id));
 		None
 	}
 }
 
-fn play_sfx(
-	state: SfxClass,
-	vox: &mut Call,
-	stealth: bool,
-	resources: &RxMap,
-	donezo: &Sender<FelyneEvt>,
-) -> Option<TrackHandle> {
-	use SfxClass::*;
-
-	if stealth {
-		return None;
-	}
+struct FelyneLoop<T> {
+	track: T,
+	start: Duration,
+	remaining: AtomicUsize,
+}
 
-	let el_list = match state {
-		NoSfx => {
-			return None;
-		},
-		Cat => SFX,
-		Bonus => BONUS_SFX,
-	};
+#[async_trait]
+impl<T: VoiceTrack> EventHandler for FelyneLoop<T> {
+	async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+		if self.remaining.load(Ordering::Relaxed) == 0 {
+			return Some(Event::Cancel);
+		}
 
-	let chan = donezo.clone();
+		self.remaining.fetch_sub(1, Ordering::Relaxed);
 
-	resources
-		.get(random_element(el_list))
-		.map(|guard| vox.play(guard.value().into()))
-		.map(move |track| {
-			let _ = track.add_event(
-				Event::Track(TrackEvent::End),
-				FelyneEndTrack {
-					chan,
-					msg: FelyneEvt::SfxEnd,
-				},
-			);
-			track
-		})
+		let _ = self.track.seek(self.start);
+		None
+	}
 }
 
-enum WaitState {
-	Limited,
-	Queued(ChannelId),
+pub async fn guild_soundscape(
+	ctx: &Context,
+	guild_state: &Arc<RwLock<GuildState>>,
+) -> Arc<Soundscape> {
+	let soundscapes = {
+		let data = ctx.data.read().await;
+		data.get::<SoundscapeKey>()
+			.cloned()
+			.expect("Soundscapes must exist after init...")
+	};
+
+	let lock = guild_state.read().await;
+	soundscapes.get(lock.soundscape().as_deref())
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 enum FelyneEvt {
 	BgmEnd,
+	BgmFade,
 	SfxEnd,
+	/// The track couldn't play this file.
+	Failed(String),
 }
 
-async fn felyne_life(
+/// What woke `felyne_life`: a message, or time to update (perhaps because a
+/// track has ended).
+enum Wake {
+	Msg(VoiceHuntMessage),
+	Tick(Option<(FelyneEvt, u128)>),
+}
+
+async fn felyne_life<D: VoiceDriver>(
 	rx: Receiver<VoiceHuntMessage>,
 	tx: Sender<VoiceHuntResponse>,
-	manager_lock: Arc<Mutex<Call>>,
+	watch_tx: WeakSender<VoiceHuntMessage>,
+	manager_lock: Arc<Mutex<D>>,
 	guild_id: GuildId,
 	vol: f32,
-	self_tx: Sender<VoiceHuntMessage>,
+	speech: SpeechWatch,
 	resources: RxMap,
-	guild_state: Arc<RwLock<GuildState>>,
-	user_states: Arc<UserState>,
-	ctx: Context,
+	host: impl HuntHost,
 ) {
 	let timer = Duration::from_millis(VOICEHUNT_FRAME_TIME);
-	let vol_range = Uniform::new(0.3, 0.4);
-	let bgm_vol_range = Uniform::new(0.15, 0.2);
-	let music_vol = 0.3;
+	let watch = DriverWatch(Arc::new(watch_tx));
+
+	let mut soundscape = host.soundscape().await;
 
-	let (sound_tx, sound_rx) = flume::bounded(2);
+	let (sound_tx, sound_rx) = flume::unbounded();
 
+	// Volume as set by users, and after ducking for speech.
+	let mut user_vol = vol;
 	let mut curr_vol = vol;
 
-	let mut curr_sfx: Option<TrackHandle> = None;
-	let mut curr_bgm: Option<TrackHandle> = None;
+	let (duck_rules, mut mix) = host.mixing().await;
+	let mut ducker = Ducker::new(duck_rules);
+
+	let mut curr_sfx: Option<D::Track> = None;
+	let mut curr_bgm: Option<D::Track> = None;
+	let mut sfx_class = SoundClass::Sfx;
+	let mut bgm_class = SoundClass::Bgm;
 
 	let mut leaving = false;
+	// Playing the outro before leaving an idle channel, without being carted.
+	let mut sleeping = false;
 
 	let mut curr_chan = None;
-	let next_chan = Arc::new(Mutex::new(None));
+	// Set while Felyne's connection has dropped: how many times she's tried
+	// to get back in, and when she'll next try.
+	let mut lost: Option<(u32, Instant)> = None;
 
 	let mut stealthy = false;
 
-	let mut bgm_machine = TimedMachine::new(BgmClass::NoBgm);
+	let mut bgm_machine = soundscape.bgm.build();
+	let mut sfx_machine = soundscape.sfx.build();
+
+	// Tracks asked for by name, played in place of the machine's next pick.
+	let mut bgm_queue: VecDeque<(String, VolumeRange)> = VecDeque::new();
+	let mut history = BgmHistory::default();
+
+	// A timeboxed quest, running alongside the BGM machine.
+	let mut quest: Option<Quest> = None;
+
+	// Outgoing tracks (and their replacements) during a crossfade.
+	let mut fades: Vec<Fade<D::Track>> = vec![];
+	let mut crossfading = false;
 
 	let mut receiver_chan = None;
-	{
-		use BgmClass::*;
-		use BgmInput::*;
-		bgm_machine
-			// Intro (only once per few runs ideally).
-			.add_priority_transition(
-				NoBgm, Intro, TryIntro, 0,
-				Some(Cooldown::new(Duration::from_secs(300).into(), false, false))
-				)
-			.add_transition(Intro, Ambience, Advance)
-			.add_transition(NoBgm, Ambience, Advance)
-			// Main ambience/bgm/bonus loop.
-			// Self.
-			.add_transition(Ambience, Ambience, Advance)
-
-			// In/out BGM.
-			.add_priority_transition(
-				Ambience, Music, Advance, 1,
-				Some(Cooldown::new(
-					Uniform::new(Duration::from_secs(300), Duration::from_secs(600)).into(),
-					true,
-					true,
-				)))
-			.add_transition(Music, Ambience, Advance)
-
-			// Poogie + Result + Out.
-			.add_priority_transition(
-				Ambience, Bonus, Advance, 2,
-				Some(Cooldown::new(
-					Uniform::new(Duration::from_secs(600), Duration::from_secs(1200)).into(),
-					true,
-					true,
-				)))
-			.add_transition(Bonus, BonusResult, Advance)
-			.add_transition(BonusResult, Ambience, Advance)
-			.all_transition(Outro, MoveOutro);
-	}
-
-	let mut sfx_machine = TimedMachine::new(SfxClass::NoSfx);
-	{
-		use SfxClass::*;
-		use SfxInput::*;
-		sfx_machine
-			.add_priority_transition(
-				NoSfx,
-				Cat,
-				Advance,
-				0,
-				Some(Cooldown::new(
-					Uniform::new(Duration::from_millis(600), Duration::from_millis(7_000)).into(),
-					true,
-					false,
-				)),
-			)
-			.add_transition(Cat, NoSfx, Advance)
-			.add_priority_transition(
-				NoSfx,
-				Bonus,
-				Advance,
-				1,
-				Some(Cooldown::new(
-					Uniform::new(Duration::from_secs(20), Duration::from_secs(60)).into(),
-					true,
-					true,
-				)),
-			)
-			.add_transition(Bonus, NoSfx, Advance);
-	}
+	let mut track_errors = 0;
+
+	// Rather than polling, sleep until we're told something, a track ends,
+	// someone starts talking, or until the next thing we know we'll need to do.
+	let mut wake_at = Some(Instant::now());
 
 	'escape: loop {
-		match rx.try_recv() {
-			Ok(VoiceHuntMessage::Channel(chan, demand, chan_users)) => {
-				let new_join = match curr_chan {
-					Some(chan_old) => chan_old != chan,
-					None => true,
-				};
+		let wake = tokio::select! {
+			biased;
+			msg = rx.recv_async() => match msg {
+				Ok(msg) => Wake::Msg(msg),
+				Err(_) => break 'escape,
+			},
+			evt = sound_rx.recv_async() => Wake::Tick(evt.ok()),
+			_ = speech.started(), if ducker.enabled() => Wake::Tick(None),
+			_ = time::sleep_until(wake_at.unwrap_or_else(Instant::now).into()),
+				if wake_at.is_some() => Wake::Tick(None),
+		};
+
+		if let Wake::Msg(_) = wake {
+			// Catch up once any other queued messages are handled.
+			wake_at = Some(Instant::now());
+		}
+
+		match wake {
+			Wake::Msg(VoiceHuntMessage::Channel(chan, chan_users)) => {
+				// Being sent anywhere mid-doze means someone's turned up.
+				let new_join = sleeping
+					|| match curr_chan {
+						Some(chan_old) => chan_old != chan,
+						None => true,
+					};
 
 				// Connect to a different vox channel.
 				let mut manager = manager_lock.lock().await;
 
 				if new_join {
-					if !demand {
-						let spawn_joiner = {
-							let mut nc = next_chan.lock().await;
-							let already_spawned = nc.is_some();
-							if already_spawned {
-								*nc = Some(WaitState::Queued(chan));
-							} else {
-								*nc = Some(WaitState::Limited);
-							}
-
-							!already_spawned
-						};
-
-						if spawn_joiner {
-							// spawn a thread and block future non-demands, but
-							// ensure that the current demand is acted upon.
-							let inner_chan = next_chan.clone();
-							let newer_tx = self_tx.clone();
-							tokio::spawn(async move {
-								tokio::time::sleep(Duration::from_secs(5)).await;
-								let mut nc = inner_chan.lock().await;
-								if let Some(WaitState::Queued(chan)) = *nc {
-									let _ = newer_tx
-										.send(VoiceHuntMessage::Channel(chan, false, chan_users));
-								}
-								*nc = None;
-							});
-						} else {
-							// don't actually join; just go home...
-							continue;
-						}
+					sleeping = false;
+					lost = None;
+
+					// Pick up any change of soundscape pack.
+					let chosen = host.soundscape().await;
+					if !Arc::ptr_eq(&chosen, &soundscape) {
+						soundscape = chosen;
+						bgm_machine = soundscape.bgm.build();
+						sfx_machine = soundscape.sfx.build();
 					}
 
 					bgm_machine.refresh();
 					sfx_machine.refresh();
 					curr_sfx = None;
 					curr_bgm = None;
+					fades.clear();
+					crossfading = false;
 
-					if manager.join(chan).await.is_ok() {
+					if manager.join(chan).await {
 						// test play
 						manager.stop();
 
-						let (opt_in, gather_mode) = {
-							let lock = guild_state.read().await;
-							(lock.server_opt(), lock.gather())
-						};
-
-						receiver_chan = listen_in(
-							&mut manager,
-							opt_in,
-							gather_mode,
-							user_states.clone(),
-							guild_state.clone(),
-							guild_id,
-							chan,
-							!stealthy,
-							chan_users,
-							ctx.clone(),
-						)
-						.await;
-
-						manager.add_global_event(TrackEvent::Error.into(), TrackErrorNotifier);
+						receiver_chan = host
+							.listen_in(&mut *manager, chan, !stealthy, chan_users)
+							.await;
+
+						manager.add_global_event(CoreEvent::VoiceTick.into(), speech.clone());
+						for evt in [
+							CoreEvent::DriverConnect,
+							CoreEvent::DriverReconnect,
+							CoreEvent::DriverDisconnect,
+						] {
+							manager.add_global_event(evt.into(), watch.clone());
+						}
 
 						let state = if let Some(s) = bgm_machine.advance(BgmInput::TryIntro) {
-							sfx_machine.cause_cooldown(
-								SfxClass::NoSfx,
-								SfxClass::Cat,
-								SfxInput::Advance,
-								0,
-								Some(Cooldown::new(Duration::from_secs(13).into(), true, true)),
-							);
-							s
+							soundscape.hush_sfx(s, &mut sfx_machine);
+							Some(s)
 						} else {
-							bgm_machine
-								.advance(BgmInput::Advance)
-								.expect("Should have reached Ambience...")
+							bgm_machine.advance(BgmInput::Advance)
 						};
 
-						curr_bgm = play_bgm(state, &mut manager, stealthy, &resources, &sound_tx)
-							.map(|track| {
-								let _ = track.set_volume(curr_vol);
+						curr_bgm = state.and_then(|state| {
+							let state = soundscape.bgm.state(state);
+							play_sound(
+								state,
+								&[],
+								&mut *manager,
+								stealthy,
+								&resources,
+								&sound_tx,
+								FelyneEvt::BgmEnd,
+							)
+							.map(|(track, file)| {
+								bgm_class = state.class;
+								let gain = state.volume.draw() * mix.multiplier(state.class);
+								let _ = track.set_volume(gain * curr_vol);
+								prepare_bgm(&track, file, &soundscape, &resources, &sound_tx);
+								history.push(file, Some(&state.name));
 								track
-							});
+							})
+						});
 
 						if stealthy {
 							// Play one sound so that discord will ACTUALLY give us voice packets...
-							curr_sfx =
-								play_sfx(SfxClass::Cat, &mut manager, false, &resources, &sound_tx)
-									.map(|track| {
-										let _ = track.set_volume(0.1 * curr_vol);
-										track
-									});
+							if let Some((ping, ping_vol)) = soundscape.stealth_ping {
+								curr_sfx = play_sound(
+									soundscape.sfx.state(ping),
+									&[],
+									&mut *manager,
+									false,
+									&resources,
+									&sound_tx,
+									FelyneEvt::SfxEnd,
+								)
+								.map(|(track, _)| {
+									let _ = track.set_volume(ping_vol * curr_vol);
+									track
+								});
+							}
 						}
 
 						curr_chan = Some(chan);
@@ -743,142 +1238,495 @@ async fn felyne_life(
 					}
 				}
 			},
-			Ok(VoiceHuntMessage::NoChannel) => {
-				quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan).await;
+			Wake::Msg(VoiceHuntMessage::NoChannel) => {
+				quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan, &host).await;
+				fades.clear();
 				curr_chan = None;
+				lost = None;
 			},
-			Ok(VoiceHuntMessage::Cart) =>
-				if leaving {
-					quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan).await;
-					break 'escape;
-				} else {
+			Wake::Msg(VoiceHuntMessage::Cart) if leaving || curr_chan.is_none() => {
+				quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan, &host).await;
+				break 'escape;
+			},
+			Wake::Msg(VoiceHuntMessage::Cart) if sleeping => {
+				// The outro's already playing: just don't wait around afterwards.
+				leaving = true;
+			},
+			Wake::Msg(VoiceHuntMessage::Sleep) if leaving || sleeping || curr_chan.is_none() => {},
+			Wake::Msg(msg @ (VoiceHuntMessage::Cart | VoiceHuntMessage::Sleep)) => {
+				if let VoiceHuntMessage::Cart = msg {
 					leaving = true;
+				} else {
+					sleeping = true;
+				}
 
-					if let Some(track) = curr_sfx.as_ref() {
-						let _ = track.pause();
-					}
+				if let Some(track) = curr_sfx.as_ref() {
+					let _ = track.pause();
+				}
 
-					curr_sfx = None;
+				curr_sfx = None;
 
-					if let Some(track) = curr_bgm.as_ref() {
-						let _ = track.pause();
-					}
+				if let Some(track) = curr_bgm.as_ref() {
+					let _ = track.pause();
+				}
 
-					let mut manager = manager_lock.lock().await;
+				for fade in fades.drain(..) {
+					fade.stop();
+				}
 
-					let state = bgm_machine
-						.advance(BgmInput::MoveOutro)
-						.expect("Can always use outro...");
+				let mut manager = manager_lock.lock().await;
 
-					curr_bgm = play_bgm(state, &mut manager, stealthy, &resources, &sound_tx).map(
-						|track| {
-							let _ = track.set_volume(0.6 * curr_vol);
-							track
-						},
-					);
+				// A soundscape without an outro just leaves once the current track ends,
+				// and nobody would hear one while disconnected.
+				let outro = bgm_machine.advance(BgmInput::MoveOutro);
+				curr_bgm = outro.filter(|_| lost.is_none()).and_then(|state| {
+					let state = soundscape.bgm.state(state);
+					play_sound(
+						state,
+						&[],
+						&mut *manager,
+						stealthy,
+						&resources,
+						&sound_tx,
+						FelyneEvt::BgmEnd,
+					)
+					.map(|(track, file)| {
+						bgm_class = state.class;
+						let gain = state.volume.draw() * mix.multiplier(state.class);
+						let _ = track.set_volume(gain * curr_vol);
+						prepare_bgm(&track, file, &soundscape, &resources, &sound_tx);
+						history.push(file, Some(&state.name));
+						track
+					})
+				});
+			},
+			Wake::Msg(VoiceHuntMessage::Disconnected) =>
+				if curr_chan.is_some() && lost.is_none() && !leaving {
+					lost = host.backoff().delay(0).map(|wait| (0, Instant::now() + wait));
 				},
-			Ok(VoiceHuntMessage::Volume(new_vol)) => {
-				if let Some(track) = curr_sfx.as_ref() {
-					let _ = track.action(move |true_track| {
-						let vol = *true_track.volume;
-						*true_track.volume = (vol / curr_vol) * new_vol;
+			Wake::Msg(VoiceHuntMessage::Reconnected) =>
+				if lost.take().is_some() {
+					info!("[VoiceHunt] {:?} reconnected by itself.", guild_id);
+				},
+			Wake::Msg(VoiceHuntMessage::Volume(new_vol)) => {
+				user_vol = new_vol;
 
+				let new_vol = user_vol * ducker.level();
+				rescale(&[&curr_sfx, &curr_bgm], curr_vol, new_vol);
+				curr_vol = new_vol;
+			},
+			Wake::Msg(VoiceHuntMessage::Duck(rules)) => {
+				ducker.set_rules(rules);
+			},
+			Wake::Msg(VoiceHuntMessage::Mix(prefs)) => {
+				rescale(
+					&[&curr_sfx],
+					mix.multiplier(sfx_class),
+					prefs.multiplier(sfx_class),
+				);
+				rescale(
+					&[&curr_bgm],
+					mix.multiplier(bgm_class),
+					prefs.multiplier(bgm_class),
+				);
+				mix = prefs;
+			},
+			Wake::Msg(VoiceHuntMessage::Sfx(file, vol)) =>
+				if !stealthy && !leaving && curr_chan.is_some() {
+					// Requested sounds play over whatever's going, and don't
+					// hold up the sfx machine.
+					let mut manager = manager_lock.lock().await;
+					if let Some(guard) = resources.get(&file) {
+						let track = manager.play(&file, guard.value());
+						let _ = track.set_volume(vol.draw() * mix.sfx * curr_vol);
+						watch_errors(&track, &file, &sound_tx);
+					}
+				},
+			Wake::Msg(VoiceHuntMessage::React(movement)) =>
+				if !stealthy && !leaving && curr_chan.is_some() {
+					if let Some(id) = soundscape.reactions.get(&movement) {
+						let state = soundscape.sfx.state(*id);
+						let mut manager = manager_lock.lock().await;
+						play_cue(
+							state,
+							&mut *manager,
+							&resources,
+							&sound_tx,
+							mix.multiplier(state.class) * curr_vol,
+						);
+					}
+				},
+			Wake::Msg(VoiceHuntMessage::Quest(cmd)) => {
+				let now = Instant::now();
+				let cue = match cmd {
+					QuestCommand::Start {
+						length,
+						warnings,
+						channel,
+					} => {
+						quest = Some(Quest::new(channel, length, warnings, now));
+						soundscape.quest.map(|cues| cues.start)
+					},
+					QuestCommand::Clear =>
+						quest.take().and(soundscape.quest).map(|cues| cues.clear),
+					QuestCommand::Abandon => {
+						quest = None;
 						None
-					});
-				}
-
-				if let Some(track) = curr_bgm.as_ref() {
-					let _ = track.action(move |true_track| {
-						let vol = *true_track.volume;
-						*true_track.volume = (vol / curr_vol) * new_vol;
-
+					},
+					QuestCommand::Pause => {
+						if let Some(q) = quest.as_mut() {
+							q.pause(now);
+						}
+						None
+					},
+					QuestCommand::Resume => {
+						if let Some(q) = quest.as_mut() {
+							q.resume(now);
+						}
 						None
-					});
+					},
+					QuestCommand::Extend(by) => {
+						if let Some(q) = quest.as_mut() {
+							q.extend(by, now);
+						}
+						None
+					},
+				};
+
+				if let Some(id) = cue.filter(|_| !stealthy && !leaving && curr_chan.is_some()) {
+					let state = soundscape.bgm.state(id);
+					let mut manager = manager_lock.lock().await;
+					play_cue(
+						state,
+						&mut *manager,
+						&resources,
+						&sound_tx,
+						mix.multiplier(state.class) * curr_vol,
+					);
 				}
+			},
+			Wake::Msg(VoiceHuntMessage::QueueBgm(file, vol)) =>
+				if bgm_queue.len() >= BGM_QUEUE_LENGTH {
+					info!(
+						"[VoiceHunt] BGM queue full for {:?}: dropping {}.",
+						guild_id, file
+					);
+				} else if !stealthy && !leaving {
+					bgm_queue.push_back((file, vol));
+				},
+			Wake::Msg(VoiceHuntMessage::Skip) =>
+				if !leaving {
+					// The track's end event moves the machine on as usual.
+					if let Some(track) = curr_bgm.as_ref() {
+						let _ = track.stop();
+					}
+				},
+			Wake::Msg(VoiceHuntMessage::Status(reply)) => {
+				let now_playing = match (curr_bgm.as_ref(), history.latest()) {
+					(Some(track), Some(played)) => {
+						let elapsed = track
+							.get_info()
+							.await
+							.map(|info| info.position)
+							.unwrap_or_else(|_| played.started.elapsed());
+						Some((played.clone(), elapsed))
+					},
+					_ => None,
+				};
 
-				curr_vol = new_vol;
+				let _ = reply.send(HuntStatus {
+					channel: curr_chan,
+					stealthy,
+					state: soundscape.bgm.state(bgm_machine.state()).name.clone(),
+					now_playing,
+					queued: bgm_queue.len(),
+					history: history.recent(),
+					quest: quest.as_ref().map(|q| q.status(Instant::now())),
+					track_errors,
+				});
 			},
-			Ok(VoiceHuntMessage::Stealth) => {
+			Wake::Msg(VoiceHuntMessage::Stealth) => {
 				stealthy = true;
+				bgm_queue.clear();
 				if let Some(chan) = &receiver_chan {
 					let _ = chan.send(ReceiverSignal::Inactive);
 				}
 			},
-			Ok(VoiceHuntMessage::Unstealth) => {
+			Wake::Msg(VoiceHuntMessage::Unstealth) => {
 				stealthy = false;
 				if let Some(chan) = &receiver_chan {
 					let _ = chan.send(ReceiverSignal::Active);
 				}
 			},
-			Err(TryRecvError::Empty) => {
-				// If we receieved nothing, then we can perform an update.
-				// Iteration, then wait.
+			Wake::Tick(woken_by) => {
 				let mut bgm_done = curr_bgm.is_none();
 				let mut sfx_done = curr_sfx.is_none();
 
-				'localcheck: loop {
-					match sound_rx.try_recv() {
-						Ok(FelyneEvt::BgmEnd) => {
+				// Never crossfade into leaving.
+				let winding_down = leaving || soundscape.bgm.state(bgm_machine.state()).terminal;
+
+				// States to draw a replacement from, for tracks which failed.
+				let mut replay_bgm = None;
+				let mut replay_sfx = None;
+
+				for evt in woken_by.into_iter().chain(sound_rx.try_iter()) {
+					match evt {
+						(FelyneEvt::BgmEnd, id) if is_current(&curr_bgm, id) => {
 							bgm_done = true;
 							curr_bgm = None;
 						},
-						Ok(FelyneEvt::SfxEnd) => {
+						(FelyneEvt::BgmFade, id) if is_current(&curr_bgm, id) && !winding_down => {
+							// Let the next track start while this one fades away.
+							if let Some(track) = curr_bgm.take() {
+								let from = match track.get_info().await {
+									Ok(info) if curr_vol > 0.0 => info.volume / curr_vol,
+									_ => 0.0,
+								};
+
+								if let Some(over) = soundscape.crossfade {
+									fades.push(Fade::new(track, from, 0.0, over));
+								}
+							}
+
+							bgm_done = true;
+							crossfading = true;
+						},
+						(FelyneEvt::SfxEnd, id) if is_current(&curr_sfx, id) => {
 							sfx_done = true;
 							curr_sfx = None;
 						},
-						_ => break 'localcheck,
+						(FelyneEvt::Failed(file), id) => {
+							mark_failed(&resources, &file, guild_id);
+							track_errors += 1;
+
+							// Nothing else will move these on.
+							if is_current(&curr_bgm, id) {
+								bgm_done = true;
+								curr_bgm = None;
+
+								// Tracks asked for by name have nothing to stand in for them.
+								replay_bgm = history
+									.latest()
+									.filter(|played| played.state.is_some())
+									.map(|_| bgm_machine.state());
+							}
+
+							if is_current(&curr_sfx, id) {
+								sfx_done = true;
+								curr_sfx = None;
+								replay_sfx = Some(sfx_machine.state());
+							}
+						},
+						// Tracks which have been skipped, or faded out.
+						_ => {},
+					}
+				}
+
+				let speaking = speech.speaking();
+				let ducked = user_vol * ducker.step(speaking);
+				if ducked != curr_vol {
+					rescale(&[&curr_sfx, &curr_bgm], curr_vol, ducked);
+					curr_vol = ducked;
+				}
+
+				fades.retain(|fade| fade.step(curr_vol));
+
+				if let Some(q) = quest.as_mut() {
+					match q.tick(Instant::now()) {
+						Some(QuestEvent::Warn(left)) => host.announce(
+							q.channel,
+							format!("Mrowr! ({} left on the quest!)", quest::time_left(left)),
+						),
+						Some(QuestEvent::TimeUp) => {
+							host.announce(
+								q.channel,
+								"Myaaa... (Time's up! The quest has failed...)".to_string(),
+							);
+							quest = None;
+
+							if let Some(cues) = soundscape
+								.quest
+								.filter(|_| !stealthy && !leaving && curr_chan.is_some())
+							{
+								let state = soundscape.bgm.state(cues.timeout);
+								let mut manager = manager_lock.lock().await;
+								play_cue(
+									state,
+									&mut *manager,
+									&resources,
+									&sound_tx,
+									mix.multiplier(state.class) * curr_vol,
+								);
+							}
+						},
+						None => {},
+					}
+				}
+
+				if let (Some((attempt, at)), Some(chan)) = (lost, curr_chan) {
+					if at <= Instant::now() {
+						let mut manager = manager_lock.lock().await;
+
+						// Joining a channel we think we're in is a no-op, so leave first
+						// to renew the session.
+						manager.leave().await;
+						if manager.join(chan).await {
+							info!("[VoiceHunt] {:?} rejoined {:?}.", guild_id, chan);
+							lost = None;
+						} else if let Some(wait) = host.backoff().delay(attempt + 1) {
+							lost = Some((attempt + 1, Instant::now() + wait));
+						} else {
+							warn!(
+								"[VoiceHunt] {:?} couldn't get back into {:?}: giving up.",
+								guild_id, chan
+							);
+							drop(manager);
+							quit_vox_channel(
+								manager_lock.clone(),
+								guild_id,
+								&mut receiver_chan,
+								&host,
+							)
+							.await;
+
+							curr_chan = None;
+							curr_sfx = None;
+							curr_bgm = None;
+							lost = None;
+							fades.clear();
+							bgm_machine.refresh();
+							sfx_machine.refresh();
+
+							host.lost_channel();
+						}
 					}
 				}
 
-				let can_play_sfx = bgm_machine.state() != BgmClass::Bonus && sfx_done;
+				// Nothing's heard while stealthy or disconnected, so don't bother
+				// moving the machines on.
+				let playing = curr_chan.is_some() && !stealthy && lost.is_none();
+				let sfx_free = |bgm_state| {
+					!soundscape.bgm.state(bgm_state).blocks_sfx
+						&& !ducker.holds_sfx(speaking)
+						&& playing
+				};
+				let bgm_free = playing && !leaving && !sleeping;
 
-				if can_play_sfx || bgm_done {
+				let can_play_sfx = sfx_done && sfx_free(bgm_machine.state());
+				let can_play_bgm = bgm_done && bgm_free;
+
+				if can_play_sfx || can_play_bgm {
 					let mut manager = manager_lock.lock().await;
 
 					if can_play_sfx {
-						if let Some(state) = sfx_machine.advance(SfxInput::Advance) {
-							if state != SfxClass::NoSfx {
-								curr_sfx =
-									play_sfx(state, &mut manager, stealthy, &resources, &sound_tx)
-										.map(|track| {
-											let mut rng = thread_rng();
-											let _ = track
-												.set_volume(vol_range.sample(&mut rng) * curr_vol);
-											track
-										});
-							}
+						if let Some(state) =
+							replay_sfx.or_else(|| sfx_machine.advance(SfxInput::Advance))
+						{
+							let state = soundscape.sfx.state(state);
+							let extra = custom_sounds(state, &host).await;
+							curr_sfx = play_sound(
+								state,
+								&extra,
+								&mut *manager,
+								stealthy,
+								&resources,
+								&sound_tx,
+								FelyneEvt::SfxEnd,
+							)
+							.map(|(track, _)| {
+								sfx_class = state.class;
+								let gain = state.volume.draw() * mix.multiplier(state.class);
+								let _ = track.set_volume(gain * curr_vol);
+								track
+							});
 						}
 					}
 
-					if bgm_done {
-						if let Some(state) = bgm_machine.advance(BgmInput::Advance) {
-							curr_bgm =
-								play_bgm(state, &mut manager, stealthy, &resources, &sound_tx).map(
-									|track| {
-										let vol = if state.no_gargwa() {
-											music_vol
-										} else {
-											let mut rng = thread_rng();
-											bgm_vol_range.sample(&mut rng)
-										} * curr_vol;
-
-										let _ = track.set_volume(vol);
-										track
-									},
-								);
+					if can_play_bgm {
+						let fade_in = soundscape.crossfade.filter(|_| crossfading);
+						crossfading = false;
+
+						if let Some((file, vol)) = bgm_queue.pop_front() {
+							curr_bgm = play_file(
+								&file,
+								&mut *manager,
+								&resources,
+								&sound_tx,
+								FelyneEvt::BgmEnd,
+							)
+							.inspect(|track| {
+								bgm_class =
+									soundscape.bgm.track_class(&file).unwrap_or(SoundClass::Bgm);
+								let gain = vol.draw() * mix.multiplier(bgm_class);
+								ramp_in(track, gain, curr_vol, fade_in, &mut fades);
+								prepare_bgm(track, &file, &soundscape, &resources, &sound_tx);
+								history.push(&file, None);
+							});
+						} else if let Some(state) = replay_bgm.or_else(|| {
+							bgm_machine
+								.advance(BgmInput::Advance)
+								.inspect(|state| soundscape.hush_sfx(*state, &mut sfx_machine))
+						}) {
+							let state = soundscape.bgm.state(state);
+							curr_bgm = play_sound(
+								state,
+								&[],
+								&mut *manager,
+								stealthy,
+								&resources,
+								&sound_tx,
+								FelyneEvt::BgmEnd,
+							)
+							.map(|(track, file)| {
+								bgm_class = state.class;
+								let gain = state.volume.draw() * mix.multiplier(state.class);
+								ramp_in(&track, gain, curr_vol, fade_in, &mut fades);
+								prepare_bgm(&track, file, &soundscape, &resources, &sound_tx);
+								history.push(file, Some(&state.name));
+								track
+							});
 						}
 					}
 				}
 
-				if bgm_machine.state() == BgmClass::Outro && bgm_done {
-					quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan).await;
-					break 'escape;
+				let terminal = soundscape.bgm.state(bgm_machine.state()).terminal;
+				if bgm_done && (leaving || sleeping || terminal) {
+					quit_vox_channel(manager_lock.clone(), guild_id, &mut receiver_chan, &host)
+						.await;
+
+					if leaving || !sleeping {
+						break 'escape;
+					}
+
+					// Nap out of the channel until we're sent back in.
+					sleeping = false;
+					curr_chan = None;
+					lost = None;
+					fades.clear();
+					bgm_machine.refresh();
+					sfx_machine.refresh();
 				}
 
-				time::sleep(timer).await;
-			},
-			Err(TryRecvError::Disconnected) => {
-				break 'escape;
+				let now = Instant::now();
+				let sfx_ready = sfx_machine
+					.ready_at(SfxInput::Advance)
+					.filter(|_| curr_sfx.is_none() && sfx_free(bgm_machine.state()));
+				let bgm_ready = bgm_machine
+					.ready_at(BgmInput::Advance)
+					.filter(|_| curr_bgm.is_none() && bgm_free);
+
+				wake_at = [
+					Some(now + timer).filter(|_| !fades.is_empty() || ducker.ramping()),
+					speech.quiet_at().filter(|_| ducker.enabled()),
+					quest.as_ref().and_then(Quest::next_event_at),
+					lost.map(|(_, at)| at),
+					sfx_ready,
+					bgm_ready,
+				]
+				.iter()
+				.flatten()
+				.min()
+				.map(|t| (*t).max(now + timer));
 			},
 		}
 	}
@@ -933,10 +1781,192 @@ pub async fn voicehunt_control(ctx: &Context, guild_id: GuildId, mode: VoiceHunt
 		.await;
 }
 
+/// Asks this guild's Felyne what she's playing, if she's out hunting.
+pub async fn voicehunt_status(ctx: &Context, guild_id: GuildId) -> Option<HuntStatus> {
+	let vhstate = {
+		let datas = ctx.data.read().await;
+		datas
+			.get::<VoiceHunt>()?
+			.get(&guild_id)
+			.map(|entry| entry.value().clone())?
+	};
+
+	let rx = vhstate.lock().await.query()?;
+
+	time::timeout(Duration::from_secs(STATUS_TIMEOUT), rx.recv_async())
+		.await
+		.ok()?
+		.ok()
+}
+
+/// Carts every active Felyne in parallel, for use when the bot is shutting down.
+pub async fn voicehunt_shutdown(data: &RwLock<TypeMap>, timeout: Duration) {
+	let states: Vec<_> = {
+		let datas = data.read().await;
+		datas
+			.get::<VoiceHunt>()
+			.map(|hunts| hunts.iter().map(|entry| entry.value().clone()).collect())
+			.unwrap_or_default()
+	};
+
+	futures::future::join_all(
+		states
+			.iter()
+			.map(|state| async move { state.lock().await.shutdown(timeout).await }),
+	)
+	.await;
+}
+
 pub async fn voicehunt_update(ctx: &Context, guild_id: GuildId, vox: VoiceState) {
 	let vhstate = try_create_vh_state(ctx, guild_id).await;
+	let rules = hunt_rules(ctx, guild_id, &[vox.user_id]).await;
+
+	vhstate
+		.lock()
+		.await
+		.set_rules(rules)
+		.register_user_state(&vox, true);
+}
+
+/// Re-chooses Felyne's channel, e.g. after a server changes which channels
+/// she may hunt in or who counts towards them.
+pub async fn voicehunt_rescan(ctx: &Context, guild_id: GuildId) {
+	let vhstate = try_create_vh_state(ctx, guild_id).await;
+	let rules = hunt_rules(ctx, guild_id, &[]).await;
+
+	let mut lock = vhstate.lock().await;
+	lock.set_rules(rules).recount();
+	lock.recalc_incumbent();
+	lock.update_channel();
+}
+
+/// Checks whether Felyne's channel has gone quiet for long enough to leave.
+async fn voicehunt_idle_check(ctx: &Context, guild_id: GuildId, at: Instant) {
+	let vhstate = {
+		let datas = ctx.data.read().await;
+		datas
+			.get::<VoiceHunt>()
+			.and_then(|vh| vh.get(&guild_id).map(|entry| entry.value().clone()))
+	};
+
+	if let Some(vhstate) = vhstate {
+		let mut lock = vhstate.lock().await;
+
+		if lock.idle_check_at == Some(at) {
+			lock.idle_check_at = None;
+		}
+
+		lock.check_idle();
+	}
+}
+
+/// Sends Felyne wherever she ought to be, or home, after she's failed to get
+/// back into a channel she dropped out of.
+async fn voicehunt_rejoin(ctx: &Context, guild_id: GuildId) {
+	let vhstate = {
+		let datas = ctx.data.read().await;
+		datas
+			.get::<VoiceHunt>()
+			.and_then(|vh| vh.get(&guild_id).map(|entry| entry.value().clone()))
+	};
+
+	if let Some(vhstate) = vhstate {
+		let mut lock = vhstate.lock().await;
+
+		// Otherwise, an unchanged choice wouldn't be sent again.
+		lock.settled = None;
+		lock.challenger = None;
+		lock.update_channel();
+	}
+}
+
+/// Re-evaluates a channel switch that was held back by `SwitchRules`.
+async fn voicehunt_recheck(ctx: &Context, guild_id: GuildId, at: Instant) {
+	let vhstate = {
+		let datas = ctx.data.read().await;
+		datas
+			.get::<VoiceHunt>()
+			.and_then(|vh| vh.get(&guild_id).map(|entry| entry.value().clone()))
+	};
+
+	if let Some(vhstate) = vhstate {
+		let mut lock = vhstate.lock().await;
+
+		if lock.recheck_at == Some(at) {
+			lock.recheck_at = None;
+		}
 
-	vhstate.lock().await.register_user_state(&vox, true);
+		lock.update_channel();
+	}
+}
+
+/// The guild's channel filter, population, switching and idle rules, and any voice channels
+/// autonomous hunts should skip regardless: the AFK channel, and those Felyne
+/// can't connect or speak in. Any of `users` known to be bots are also noted.
+async fn hunt_rules(ctx: &Context, guild_id: GuildId, users: &[UserId]) -> HuntRules {
+	let guild_state = {
+		let datas = ctx.data.read().await;
+		datas
+			.get::<GuildStates>()
+			.and_then(|gs| gs.get(&guild_id).map(|entry| entry.value().clone()))
+	};
+
+	let (filter, population, switching, reactions, idle) = match guild_state {
+		Some(gs) => {
+			let lock = gs.read().await;
+			(
+				lock.channel_filter().clone(),
+				lock.population_rules(),
+				lock.switch_rules(),
+				lock.reaction_rules(),
+				lock.idle_rules(),
+			)
+		},
+		None => Default::default(),
+	};
+
+	let bots = users
+		.iter()
+		.filter(|user| ctx.cache.user(**user).map(|u| u.bot).unwrap_or(false))
+		.copied()
+		.collect();
+
+	let me = ctx.cache.current_user().id;
+	let avoided = ctx
+		.cache
+		.guild(guild_id)
+		.map(|guild| {
+			let afk = guild.afk_metadata.as_ref().map(|afk| afk.afk_channel_id);
+			let member = guild.members.get(&me);
+
+			guild
+				.channels
+				.values()
+				.filter(|chan| matches!(chan.kind, ChannelType::Voice | ChannelType::Stage))
+				.filter(|chan| {
+					let muted = member
+						.map(|m| {
+							let perms = guild.user_permissions_in(chan, m);
+							!(perms.connect() && perms.speak())
+						})
+						.unwrap_or(false);
+
+					Some(chan.id) == afk || muted
+				})
+				.map(|chan| chan.id)
+				.collect()
+		})
+		.unwrap_or_default();
+
+	HuntRules {
+		filter,
+		avoided,
+		population,
+		switching,
+		reactions,
+		idle,
+		bots,
+	}
 }
 
 pub async fn voicehunt_complete_update(
@@ -945,8 +1975,14 @@ pub async fn voicehunt_complete_update(
 	voice_states: HashMap<UserId, VoiceState>,
 ) {
 	let vhstate = try_create_vh_state(ctx, guild_id).await;
+	let users = voice_states.keys().copied().collect::<Vec<_>>();
+	let rules = hunt_rules(ctx, guild_id, &users).await;
 
-	vhstate.lock().await.register_user_states(voice_states);
+	vhstate
+		.lock()
+		.await
+		.set_rules(rules)
+		.register_user_states(voice_states);
 }
 
 async fn try_create_vh_state(ctx: &Context, guild_id: GuildId) -> Arc<Mutex<VHState>> {
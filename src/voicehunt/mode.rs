@@ -1,6 +1,9 @@
 use crate::VoiceHuntCommand;
 use enum_primitive::*;
-use serenity::{client::Context, model::id::ChannelId};
+use serenity::{
+	client::Context,
+	model::id::{ChannelId, UserId},
+};
 use tokio_postgres::Row;
 
 enum_from_primitive! {
@@ -11,6 +14,7 @@ pub enum JoinMode {
 	Hunt,
 	DirectedHunt,
 	Watch,
+	Follow,
 }
 }
 
@@ -20,6 +24,7 @@ pub enum Join {
 	Hunt,
 	DirectedHunt(ChannelId),
 	Watch,
+	Follow(UserId),
 }
 
 impl Join {
@@ -29,6 +34,7 @@ impl Join {
 			Self::Hunt => JoinMode::Hunt,
 			Self::DirectedHunt(_) => JoinMode::DirectedHunt,
 			Self::Watch => JoinMode::Watch,
+			Self::Follow(_) => JoinMode::Follow,
 		}) as i32
 	}
 
@@ -39,6 +45,13 @@ impl Join {
 		}
 	}
 
+	pub fn to_user(self) -> Option<i64> {
+		match self {
+			Self::Follow(a) => Some(i64::from(a)),
+			_ => None,
+		}
+	}
+
 	pub async fn user_friendly_print(&self, ctx: &Context) -> String {
 		match self {
 			Self::Carted => "taking a break".to_string(),
@@ -48,6 +61,10 @@ impl Join {
 				Ok(chan) => format!("hunting in `{}`", chan),
 				Err(_) => format!("hunting in a channel with the ID {}", r),
 			},
+			Self::Follow(u) => match u.to_user(ctx).await {
+				Ok(user) => format!("following `{}`", user.name),
+				Err(_) => format!("following a user with the ID {}", u),
+			},
 		}
 	}
 
@@ -57,6 +74,7 @@ impl Join {
 			Self::Hunt => VoiceHuntCommand::BraveHunt,
 			Self::DirectedHunt(c) => VoiceHuntCommand::DirectedHunt(*c),
 			Self::Watch => VoiceHuntCommand::Stalk,
+			Self::Follow(u) => VoiceHuntCommand::Follow(*u),
 		}
 	}
 }
@@ -73,6 +91,10 @@ impl From<&Row> for Join {
 				Self::DirectedHunt(ChannelId::new(i_role as u64))
 			},
 			JoinMode::Watch => Self::Watch,
+			JoinMode::Follow => {
+				let i_user: i64 = row.get(2);
+				Self::Follow(UserId::new(i_user as u64))
+			},
 		}
 	}
 }
@@ -0,0 +1,217 @@
+use serenity::model::prelude::ChannelId;
+use std::time::{Duration, Instant};
+
+/// Changes to a guild's quest timer, as asked for via `!quest`.
+#[derive(Debug)]
+pub enum QuestCommand {
+	/// Begins a quest of `length`, posting to `channel` whenever only one of
+	/// `warnings` is left.
+	Start {
+		length: Duration,
+		warnings: Vec<Duration>,
+		channel: ChannelId,
+	},
+	Pause,
+	Resume,
+	Extend(Duration),
+	/// Ends the quest early, in success.
+	Clear,
+	Abandon,
+}
+
+/// A running quest, as reported by `felyne_life`.
+#[derive(Clone, Copy, Debug)]
+pub struct QuestStatus {
+	pub remaining: Duration,
+	pub paused: bool,
+}
+
+/// How much of a quest is left, e.g. `5 minutes` or `4:30`.
+pub fn time_left(left: Duration) -> String {
+	let secs = left.as_secs();
+
+	match (secs / 60, secs % 60) {
+		(1, 0) => "1 minute".to_string(),
+		(mins, 0) => format!("{} minutes", mins),
+		(mins, secs) => format!("{}:{:02}", mins, secs),
+	}
+}
+
+/// Something a quest needs announced.
+#[derive(Debug, Eq, PartialEq)]
+pub(super) enum QuestEvent {
+	Warn(Duration),
+	TimeUp,
+}
+
+#[derive(Debug)]
+enum Clock {
+	Running { ends: Instant },
+	Paused { left: Duration },
+}
+
+/// A timeboxed quest: a countdown which can be paused and extended, warning
+/// as it passes each of its checkpoints.
+#[derive(Debug)]
+pub(super) struct Quest {
+	pub channel: ChannelId,
+	clock: Clock,
+	warnings: Vec<Duration>,
+	/// Warnings yet to be given, largest last.
+	pending: Vec<Duration>,
+}
+
+impl Quest {
+	pub fn new(
+		channel: ChannelId,
+		length: Duration,
+		warnings: Vec<Duration>,
+		now: Instant,
+	) -> Self {
+		let mut out = Self {
+			channel,
+			clock: Clock::Running { ends: now + length },
+			warnings,
+			pending: vec![],
+		};
+
+		out.rearm(now);
+
+		out
+	}
+
+	pub fn remaining(&self, now: Instant) -> Duration {
+		match self.clock {
+			Clock::Running { ends } => ends.saturating_duration_since(now),
+			Clock::Paused { left } => left,
+		}
+	}
+
+	pub fn status(&self, now: Instant) -> QuestStatus {
+		QuestStatus {
+			remaining: self.remaining(now),
+			paused: matches!(self.clock, Clock::Paused { .. }),
+		}
+	}
+
+	pub fn pause(&mut self, now: Instant) {
+		if let Clock::Running { .. } = self.clock {
+			self.clock = Clock::Paused {
+				left: self.remaining(now),
+			};
+		}
+	}
+
+	pub fn resume(&mut self, now: Instant) {
+		if let Clock::Paused { left } = self.clock {
+			self.clock = Clock::Running { ends: now + left };
+		}
+	}
+
+	/// Adds `by` to the clock, re-arming any warnings this pushes back out of reach.
+	pub fn extend(&mut self, by: Duration, now: Instant) {
+		self.clock = match self.clock {
+			Clock::Running { ends } => Clock::Running { ends: ends + by },
+			Clock::Paused { left } => Clock::Paused { left: left + by },
+		};
+
+		self.rearm(now);
+	}
+
+	/// When `tick` will next have something to say, unless paused.
+	pub fn next_event_at(&self) -> Option<Instant> {
+		match self.clock {
+			Clock::Running { ends } => self
+				.pending
+				.last()
+				.map_or(Some(ends), |warning| ends.checked_sub(*warning)),
+			Clock::Paused { .. } => None,
+		}
+	}
+
+	/// Checks the clock, returning the most pressing warning just passed
+	/// (if any), or whether time has run out.
+	pub fn tick(&mut self, now: Instant) -> Option<QuestEvent> {
+		if let Clock::Paused { .. } = self.clock {
+			return None;
+		}
+
+		let left = self.remaining(now);
+		if left.is_zero() {
+			return Some(QuestEvent::TimeUp);
+		}
+
+		let mut passed = None;
+		while let Some(warning) = self.pending.last().copied().filter(|w| *w >= left) {
+			self.pending.pop();
+			passed = Some(warning);
+		}
+
+		passed.map(QuestEvent::Warn)
+	}
+
+	fn rearm(&mut self, now: Instant) {
+		let left = self.remaining(now);
+
+		self.pending = self
+			.warnings
+			.iter()
+			.copied()
+			.filter(|w| *w < left && !w.is_zero())
+			.collect();
+		self.pending.sort_unstable();
+		self.pending.dedup();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn mins(n: u64) -> Duration {
+		Duration::from_secs(n * 60)
+	}
+
+	#[test]
+	fn test_quest_warns_then_times_out() {
+		let start = Instant::now();
+		let mut quest = Quest::new(ChannelId::new(1), mins(20), vec![mins(1), mins(10)], start);
+
+		assert_eq!(quest.tick(start), None);
+		assert_eq!(quest.next_event_at(), Some(start + mins(10)));
+		assert_eq!(
+			quest.tick(start + mins(10)),
+			Some(QuestEvent::Warn(mins(10)))
+		);
+		assert_eq!(quest.tick(start + mins(11)), None);
+
+		// Only the most pressing of several missed warnings is given.
+		let mut late = Quest::new(ChannelId::new(1), mins(20), vec![mins(1), mins(10)], start);
+		assert_eq!(late.tick(start + mins(19)), Some(QuestEvent::Warn(mins(1))));
+		assert_eq!(late.tick(start + mins(20)), Some(QuestEvent::TimeUp));
+	}
+
+	#[test]
+	fn test_quest_pause_and_extend() {
+		let start = Instant::now();
+		let mut quest = Quest::new(ChannelId::new(1), mins(10), vec![mins(5)], start);
+
+		quest.pause(start + mins(2));
+		assert_eq!(quest.next_event_at(), None);
+		assert_eq!(quest.tick(start + mins(30)), None);
+		assert_eq!(quest.remaining(start + mins(30)), mins(8));
+
+		quest.resume(start + mins(30));
+		assert_eq!(
+			quest.tick(start + mins(33)),
+			Some(QuestEvent::Warn(mins(5)))
+		);
+
+		quest.extend(mins(10), start + mins(33));
+		assert_eq!(quest.remaining(start + mins(33)), mins(15));
+		assert_eq!(
+			quest.tick(start + mins(43)),
+			Some(QuestEvent::Warn(mins(5)))
+		);
+	}
+}
@@ -1,8 +1,9 @@
-use super::live::LiveTrace;
+use super::{driver::VoiceDriver, live::LiveTrace};
 use crate::{
-	config::{GatherMode, OptInOut},
-	constants::TRACE_DIR,
+	config::{GatherMode, NoticeStatus, OptInOut},
+	dbs::{Db, FelyneDb},
 	guild::GuildState,
+	trace_sink::{TraceManifest, TraceSinkKey, TraceStore, WriteGuard},
 	user::UserState,
 };
 use felyne_trace::FelyneTrace;
@@ -12,10 +13,7 @@ use serenity::{
 	client::Context,
 	model::prelude::{Channel, ChannelId, GuildId, UserId},
 };
-use songbird::{
-	events::{CoreEvent, Event, EventContext, EventHandler},
-	Call,
-};
+use songbird::events::{CoreEvent, Event, EventContext, EventHandler};
 use std::{
 	sync::{
 		atomic::{AtomicBool, Ordering},
@@ -23,8 +21,8 @@ use std::{
 	},
 	time::{Instant, SystemTime},
 };
-use tokio::{fs::File, sync::RwLock};
-use tracing::error;
+use tokio::sync::RwLock;
+use tracing::{error, warn};
 
 #[derive(Clone)]
 pub struct VoiceHuntReceiver {
@@ -34,10 +32,13 @@ pub struct VoiceHuntReceiver {
 
 	never_act: Arc<AtomicBool>,
 	do_nothing: Arc<AtomicBool>,
+	announced: Arc<AtomicBool>,
 	gather_mode: GatherMode,
 	user_states: Arc<UserState>,
 	guild_id: GuildId,
+	channel_id: ChannelId,
 	guild_state: Arc<RwLock<GuildState>>,
+	store: Option<Arc<TraceStore>>,
 
 	ctx: Context,
 }
@@ -56,12 +57,25 @@ impl VoiceHuntReceiver {
 	) -> Self {
 		let (tx, rx) = flume::bounded(1);
 
-		let never_act = opt_in.opted_out();
+		let store = {
+			let data = ctx.data.read().await;
+			data.get::<TraceSinkKey>().cloned()
+		};
+
+		let over_quota = store.as_ref().map(|s| s.over_quota()).unwrap_or(false);
+		if over_quota && !opt_in.opted_out() {
+			warn!(
+				"Trace storage over quota: not capturing in {:?} for {:?}.",
+				channel_id, guild_id
+			);
+		}
+
+		let never_act = opt_in.opted_out() || over_quota;
 		let prevent = match gather_mode {
 			GatherMode::AlwaysGather => false,
 			GatherMode::GatherActive => !making_noise,
 		};
-		let do_nothing = Arc::new((never_act || prevent).into());
+		let do_nothing = never_act || prevent;
 
 		let label = {
 			let lock = guild_state.read().await;
@@ -71,6 +85,15 @@ impl VoiceHuntReceiver {
 		let user_id = ctx.http.get_current_user().await.ok().map(|cu| cu.id);
 		let rtc_region = None;
 
+		if !do_nothing {
+			post_consent_notice(
+				guild_state.clone(),
+				channel_id,
+				NoticeStatus::Started,
+				ctx.clone(),
+			);
+		}
+
 		Self {
 			trace: Arc::new(RwLock::new(Some(LiveTrace::new(
 				Instant::now(),
@@ -83,19 +106,28 @@ impl VoiceHuntReceiver {
 			tx,
 
 			never_act: Arc::new(never_act.into()),
-			do_nothing,
+			do_nothing: Arc::new(do_nothing.into()),
+			announced: Arc::new((!do_nothing).into()),
 			gather_mode,
 			user_states,
 			guild_id,
+			channel_id,
 			guild_state,
+			store,
 			ctx,
 		}
 	}
 
 	fn handle_possible_cancel(&self) -> bool {
 		match self.rx.try_recv() {
-			Ok(ReceiverSignal::Poison) | Err(TryRecvError::Disconnected) => {
-				let _ = self.tx.send(ReceiverSignal::Poison);
+			Ok(ReceiverSignal::Poison(write)) => {
+				// Put the write back: it's released once every clone is dropped,
+				// by which point `Drop` has registered its own.
+				let _ = self.tx.send(ReceiverSignal::Poison(write));
+				true
+			},
+			Err(TryRecvError::Disconnected) => {
+				let _ = self.tx.send(ReceiverSignal::Poison(None));
 				true
 			},
 			Ok(ReceiverSignal::Active) => {
@@ -103,6 +135,10 @@ impl VoiceHuntReceiver {
 
 				self.do_nothing.store(never_act, Ordering::Relaxed);
 
+				if !never_act {
+					self.announce(true, NoticeStatus::Started);
+				}
+
 				false
 			},
 			Ok(ReceiverSignal::Inactive) => {
@@ -112,11 +148,57 @@ impl VoiceHuntReceiver {
 				self.do_nothing
 					.store(never_act || prevent, Ordering::Relaxed);
 
+				if prevent {
+					self.announce(false, NoticeStatus::Paused);
+				}
+
 				false
 			},
 			Err(TryRecvError::Empty) => false,
 		}
 	}
+
+	/// Posts a consent notice, but only if measurement actually changed state.
+	fn announce(&self, active: bool, status: NoticeStatus) {
+		if self.announced.swap(active, Ordering::Relaxed) != active {
+			post_consent_notice(
+				self.guild_state.clone(),
+				self.channel_id,
+				status,
+				self.ctx.clone(),
+			);
+		}
+	}
+}
+
+fn post_consent_notice(
+	guild_state: Arc<RwLock<GuildState>>,
+	channel_id: ChannelId,
+	status: NoticeStatus,
+	ctx: Context,
+) {
+	tokio::spawn(async move {
+		let (notice, prefix) = {
+			let lock = guild_state.read().await;
+			(
+				lock.consent_notice().clone(),
+				lock.custom_prefix()
+					.clone()
+					.unwrap_or_else(|| "!".to_string()),
+			)
+		};
+
+		if let Some(notice) = notice {
+			let text = notice.render(status, channel_id, &prefix);
+
+			if let Err(e) = notice.channel.say(&ctx.http, text).await {
+				warn!(
+					"Couldn't post consent notice to {:?}: {:?}",
+					notice.channel, e
+				);
+			}
+		}
+	});
 }
 
 // Disable voice hunt receive: I'm not too interested in measuring this anymore.
@@ -197,10 +279,17 @@ impl Drop for VoiceHuntReceiver {
 		// Joining a new channel will cause the old receiver to be dropped.
 		// Similarly, leaving completely will do the same...
 
+		self.announce(false, NoticeStatus::Stopped);
+
+		// Register the write now, so that shutdown can't miss it.
+		let guard = self.store.as_ref().map(|store| store.begin_write());
+
 		finalise_audio_session(
 			self.trace.clone(),
 			self.user_states.clone(),
 			self.guild_state.clone(),
+			self.store.clone(),
+			guard,
 			self.ctx.clone(),
 		);
 	}
@@ -210,10 +299,16 @@ fn finalise_audio_session(
 	trace: Arc<RwLock<Option<LiveTrace>>>,
 	user_data: Arc<UserState>,
 	guild_state: Arc<RwLock<GuildState>>,
+	store: Option<Arc<TraceStore>>,
+	guard: Option<WriteGuard>,
 	ctx: Context,
 ) {
 	tokio::spawn(async move {
-		let anonymised: Option<FelyneTrace> = {
+		let _guard = guard;
+
+		let guild_id = guild_state.read().await.guild();
+
+		let anonymised: Option<(FelyneTrace, Vec<UserId>)> = {
 			let mut lock = trace.write().await;
 
 			if let Some(mut trace) = lock.take() {
@@ -223,17 +318,23 @@ fn finalise_audio_session(
 			}
 		};
 
+		let db = {
+			let data = ctx.data.read().await;
+			data.get::<Db>().cloned()
+		};
+
 		let time_name = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
 		if let Ok(t) = time_name {
-			let fname = format!("{}/{}.bc", TRACE_DIR, t.as_micros());
-			if let Some(trace) = &anonymised {
-				let out = File::create(&fname).await;
-
-				if let Ok(out) = out {
-					if let Err(e) = felyne_trace::write_async(out, &trace).await {
-						error!("Failed to write trace: {:?}", e);
+			let key = format!("{}.bc", t.as_micros());
+			match (&anonymised, store) {
+				(Some((trace, contributors)), Some(store)) => {
+					// Errors are logged by the store, after retries.
+					if let (Ok(()), Some(db)) = (store.save(&key, trace).await, db) {
+						record_trace(&db, &key, trace, guild_id, contributors).await;
 					}
-				}
+				},
+				(Some(_), None) => error!("No trace sink installed: dropping trace {}.", key),
+				_ => {},
 			}
 		} else {
 			error!("Apparently times are hard.");
@@ -241,14 +342,35 @@ fn finalise_audio_session(
 	});
 }
 
+async fn record_trace(
+	db: &FelyneDb,
+	key: &str,
+	trace: &FelyneTrace,
+	guild_id: GuildId,
+	contributors: &[UserId],
+) {
+	if let Some(manifest) = TraceManifest::new(key, trace) {
+		if let Err(e) = db.upsert_trace_manifest(&manifest).await {
+			error!("Nya?! (Couldn't write trace_manifest db updates.){:?}", e);
+		}
+	}
+
+	db.mark_guild_ack_used(guild_id).await;
+
+	if !contributors.is_empty() {
+		db.mark_user_acks_used(contributors).await;
+	}
+}
+
 pub enum ReceiverSignal {
 	Active,
 	Inactive,
-	Poison,
+	/// Stop measuring, holding onto this pending write until the trace is saved.
+	Poison(Option<WriteGuard>),
 }
 
 pub async fn listen_in(
-	handler: &mut Call,
+	handler: &mut impl VoiceDriver,
 	opt_in: OptInOut,
 	gather_mode: GatherMode,
 	user_states: Arc<UserState>,
@@ -0,0 +1,81 @@
+use super::quest::QuestStatus;
+use crate::{constants::BGM_HISTORY_LENGTH, soundscape::track_stem};
+use serenity::model::prelude::ChannelId;
+use std::{
+	collections::VecDeque,
+	time::{Duration, Instant},
+};
+
+/// A BGM track Felyne has played, and why.
+#[derive(Clone, Debug)]
+pub struct PlayedTrack {
+	/// File name, minus folder and extension (e.g., `5815`).
+	pub track: String,
+	/// The state which chose this track, or `None` if someone asked for it.
+	pub state: Option<String>,
+	pub started: Instant,
+}
+
+/// What a guild's Felyne is up to, as reported by `felyne_life`.
+#[derive(Clone, Debug)]
+pub struct HuntStatus {
+	pub channel: Option<ChannelId>,
+	pub stealthy: bool,
+	/// Current state of the BGM machine.
+	pub state: String,
+	/// The current BGM track, and how far through it Felyne is.
+	pub now_playing: Option<(PlayedTrack, Duration)>,
+	pub queued: usize,
+	/// Newest first.
+	pub history: Vec<PlayedTrack>,
+	pub quest: Option<QuestStatus>,
+	/// Tracks which have failed to play on this hunt.
+	pub track_errors: usize,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct BgmHistory {
+	played: VecDeque<PlayedTrack>,
+}
+
+impl BgmHistory {
+	pub fn push(&mut self, file: &str, state: Option<&str>) {
+		if self.played.len() >= BGM_HISTORY_LENGTH {
+			self.played.pop_front();
+		}
+
+		self.played.push_back(PlayedTrack {
+			track: track_stem(file).to_string(),
+			state: state.map(str::to_string),
+			started: Instant::now(),
+		});
+	}
+
+	pub fn latest(&self) -> Option<&PlayedTrack> {
+		self.played.back()
+	}
+
+	pub fn recent(&self) -> Vec<PlayedTrack> {
+		self.played.iter().rev().cloned().collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_history_keeps_newest() {
+		let mut history = BgmHistory::default();
+
+		for i in 0..BGM_HISTORY_LENGTH + 3 {
+			history.push(&format!("bgm/{}.opus", i), Some("music"));
+		}
+
+		let recent = history.recent();
+
+		assert_eq!(recent.len(), BGM_HISTORY_LENGTH);
+		assert_eq!(recent[0].track, (BGM_HISTORY_LENGTH + 2).to_string());
+		assert_eq!(history.latest().unwrap().track, recent[0].track);
+	}
+}
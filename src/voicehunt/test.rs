@@ -0,0 +1,511 @@
+//! `felyne_life`, driven headless through a fake voice connection.
+
+use super::{
+	driver::fake::{Action, FakeDriver, FakeLog, FakeTrack},
+	host::fake::FakeHost,
+	*,
+};
+use crate::{audio_resources::add_resource, trace_sink::TraceStore};
+use parking_lot::Mutex as SyncMutex;
+use std::sync::atomic::AtomicUsize;
+
+const DEFAULT: &str = include_str!("../../soundscapes/default.json");
+const INTRO: &[&str] = &[
+	"bgm/5839.opus",
+	"bgm/5840.opus",
+	"bgm/5841.opus",
+	"bgm/5842.opus",
+];
+const OUTRO: &str = "bgm/5799.opus";
+
+struct Hunt {
+	tx: Sender<VoiceHuntMessage>,
+	done: Receiver<VoiceHuntResponse>,
+	log: FakeLog,
+	said: Arc<SyncMutex<Vec<(ChannelId, String)>>>,
+	lost: Arc<AtomicUsize>,
+	resources: RxMap,
+	capture: Receiver<ReceiverSignal>,
+	store: Arc<TraceStore>,
+}
+
+impl Hunt {
+	fn start() -> Self {
+		let soundscape = Arc::new(Soundscape::from_json(DEFAULT).unwrap());
+
+		let resources: RxMap = Arc::new(DashMap::new());
+		for file in soundscape.tracks() {
+			add_resource(&resources, file);
+		}
+
+		let (tx, rx) = flume::unbounded();
+		let (done_tx, done) = flume::unbounded();
+		let driver = FakeDriver::default();
+		let log = driver.log.clone();
+		let said = Arc::new(SyncMutex::new(vec![]));
+		let lost = Arc::new(AtomicUsize::new(0));
+		let (capture_tx, capture) = flume::unbounded();
+		let store = Arc::new(TraceStore::in_dir(
+			std::env::temp_dir().join("felyne-hunt-test"),
+		));
+
+		tokio::spawn(felyne_life(
+			rx,
+			done_tx,
+			tx.downgrade(),
+			Arc::new(Mutex::new(driver)),
+			GuildId::new(1),
+			1.0,
+			SpeechWatch::new(),
+			resources.clone(),
+			FakeHost {
+				soundscape,
+				said: said.clone(),
+				lost: lost.clone(),
+				capture: capture_tx,
+				store: store.clone(),
+			},
+		));
+
+		Self {
+			tx,
+			done,
+			log,
+			said,
+			lost,
+			resources,
+			capture,
+			store,
+		}
+	}
+
+	fn send(&self, msg: VoiceHuntMessage) {
+		self.tx.send(msg).unwrap();
+	}
+
+	async fn status(&self) -> HuntStatus {
+		let (tx, rx) = flume::bounded(1);
+		self.send(VoiceHuntMessage::Status(tx));
+		rx.recv_async().await.unwrap()
+	}
+
+	fn count(&self, action: &Action) -> usize {
+		self.log.actions().iter().filter(|a| *a == action).count()
+	}
+
+	fn plays(&self) -> Vec<String> {
+		self.log
+			.actions()
+			.into_iter()
+			.filter_map(|a| match a {
+				Action::Play(file) => Some(file),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Waits for a new BGM track to start, returning it.
+	async fn bgm_after(&self, old: Option<u128>) -> FakeTrack {
+		let mut out = None;
+		eventually("a new bgm track", || {
+			out = self.log.playing("bgm/").filter(|t| Some(t.id()) != old);
+			out.is_some()
+		})
+		.await;
+
+		out.unwrap()
+	}
+
+	async fn is_done(&self) -> bool {
+		matches!(
+			time::timeout(Duration::from_secs(2), self.done.recv_async()).await,
+			Ok(Ok(VoiceHuntResponse::Done))
+		)
+	}
+}
+
+async fn eventually(what: &str, mut check: impl FnMut() -> bool) {
+	let deadline = Instant::now() + Duration::from_secs(2);
+
+	while !check() {
+		assert!(Instant::now() < deadline, "Timed out waiting for {}.", what);
+		time::sleep(Duration::from_millis(5)).await;
+	}
+}
+
+#[tokio::test]
+async fn test_joins_and_plays_intro() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let intro = hunt.bgm_after(None).await;
+
+	assert!(INTRO.contains(&intro.file().as_str()));
+	assert_eq!(intro.volume(), 1.0);
+	assert_eq!(
+		hunt.log.actions()[..2],
+		[Action::Join(chan), Action::StopAll]
+	);
+
+	let status = hunt.status().await;
+	assert_eq!(status.channel, Some(chan));
+	assert_eq!(status.state, "intro");
+}
+
+#[tokio::test]
+async fn test_track_end_moves_bgm_on() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	intro.finish().await;
+
+	hunt.bgm_after(Some(intro.id())).await;
+	assert_eq!(hunt.status().await.state, "ambience");
+}
+
+#[tokio::test]
+async fn test_skip_stops_current_bgm() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Skip);
+
+	hunt.bgm_after(Some(intro.id())).await;
+	assert!(intro.is_done());
+}
+
+#[tokio::test]
+async fn test_volume_rescales_playing_tracks() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Volume(0.5));
+
+	eventually("the intro to quieten", || intro.volume() == 0.5).await;
+}
+
+#[tokio::test]
+async fn test_switching_channels_rejoins() {
+	let hunt = Hunt::start();
+	let (a, b) = (ChannelId::new(10), ChannelId::new(11));
+
+	hunt.send(VoiceHuntMessage::Channel(a, 2));
+	let intro = hunt.bgm_after(None).await;
+
+	// Being told about the same channel again changes nothing.
+	hunt.send(VoiceHuntMessage::Channel(a, 3));
+	hunt.send(VoiceHuntMessage::Channel(b, 2));
+	let next = hunt.bgm_after(Some(intro.id())).await;
+
+	assert_eq!(hunt.count(&Action::Join(a)), 1);
+	assert_eq!(hunt.count(&Action::Join(b)), 1);
+	assert!(intro.is_done());
+	// The intro's on cooldown.
+	assert!(!INTRO.contains(&next.file().as_str()));
+	assert_eq!(hunt.status().await.channel, Some(b));
+}
+
+#[tokio::test]
+async fn test_stealth_only_pings() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Stealth);
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	hunt.send(VoiceHuntMessage::Sfx(
+		"sfx/maow1.opus".into(),
+		VolumeRange::default(),
+	));
+
+	let status = hunt.status().await;
+	assert!(status.stealthy);
+
+	let plays = hunt.plays();
+	assert_eq!(plays.len(), 1);
+	assert!(plays[0].starts_with("sfx/"));
+	assert_eq!(hunt.log.tracks()[0].volume(), 0.1);
+}
+
+#[tokio::test]
+async fn test_failed_join_plays_nothing() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.log.refuse(true);
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+
+	assert_eq!(hunt.status().await.channel, None);
+	assert_eq!(hunt.log.actions(), [Action::Join(chan)]);
+}
+
+#[tokio::test]
+async fn test_no_channel_leaves() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::NoChannel);
+
+	assert_eq!(hunt.status().await.channel, None);
+	assert_eq!(hunt.log.actions().last(), Some(&Action::Leave));
+	assert!(intro.is_done());
+}
+
+#[tokio::test]
+async fn test_cart_without_channel_is_immediate() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Cart);
+
+	assert!(hunt.is_done().await);
+	assert!(hunt.plays().is_empty());
+}
+
+#[tokio::test]
+async fn test_cart_waits_for_outro() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Cart);
+
+	let outro = hunt.bgm_after(Some(intro.id())).await;
+	assert_eq!(outro.file(), OUTRO);
+	assert!(hunt.done.is_empty());
+	assert_eq!(hunt.count(&Action::Leave), 0);
+
+	outro.finish().await;
+
+	assert!(hunt.is_done().await);
+	assert_eq!(hunt.log.actions().last(), Some(&Action::Leave));
+}
+
+#[tokio::test]
+async fn test_leaving_holds_flush_until_capture_is_done() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::NoChannel);
+
+	// The capture is told to stop, and handed the write it has yet to make.
+	let signal = time::timeout(Duration::from_secs(2), hunt.capture.recv_async())
+		.await
+		.unwrap()
+		.unwrap();
+	assert!(matches!(signal, ReceiverSignal::Poison(Some(_))));
+
+	let flush = tokio::spawn({
+		let store = hunt.store.clone();
+		async move { store.flush().await }
+	});
+
+	time::sleep(Duration::from_millis(50)).await;
+	assert!(!flush.is_finished());
+
+	drop(signal);
+
+	time::timeout(Duration::from_secs(1), flush)
+		.await
+		.expect("flush should finish once the capture is saved")
+		.unwrap();
+}
+
+#[tokio::test]
+async fn test_sleep_leaves_until_sent_back() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Sleep);
+
+	let outro = hunt.bgm_after(Some(intro.id())).await;
+	assert_eq!(outro.file(), OUTRO);
+	outro.finish().await;
+
+	eventually("Felyne to leave", || hunt.count(&Action::Leave) == 1).await;
+	assert_eq!(hunt.status().await.channel, None);
+
+	// Still alive, and happy to head back in.
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let next = hunt.bgm_after(Some(outro.id())).await;
+
+	assert_eq!(hunt.count(&Action::Join(chan)), 2);
+	assert_ne!(next.file(), OUTRO);
+	assert!(hunt.done.is_empty());
+}
+
+#[tokio::test]
+async fn test_quest_timeout_announces_and_cues() {
+	let hunt = Hunt::start();
+	let (voice, text) = (ChannelId::new(10), ChannelId::new(20));
+
+	hunt.send(VoiceHuntMessage::Channel(voice, 2));
+	hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Quest(QuestCommand::Start {
+		length: Duration::from_millis(50),
+		warnings: vec![],
+		channel: text,
+	}));
+
+	eventually("the quest to fail", || !hunt.said.lock().is_empty()).await;
+
+	assert_eq!(hunt.said.lock()[0].0, text);
+	eventually("the quest cues", || {
+		let plays = hunt.plays();
+		plays.contains(&"bgm/5796.opus".to_string()) && plays.contains(&OUTRO.to_string())
+	})
+	.await;
+	assert!(hunt.status().await.quest.is_none());
+}
+
+#[tokio::test]
+async fn test_reconnecting_by_itself_cancels_rejoin() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.send(VoiceHuntMessage::Disconnected);
+	hunt.send(VoiceHuntMessage::Reconnected);
+
+	// Give a rejoin the chance to (wrongly) happen.
+	time::sleep(Duration::from_millis(50)).await;
+
+	assert_eq!(hunt.status().await.channel, Some(chan));
+	assert_eq!(hunt.count(&Action::Join(chan)), 1);
+	assert_eq!(hunt.count(&Action::Leave), 0);
+	assert!(!intro.is_done());
+}
+
+#[tokio::test]
+async fn test_rejoins_after_drop() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let intro = hunt.bgm_after(None).await;
+
+	// Fail once, then let the second try through.
+	hunt.log.refuse(true);
+	hunt.send(VoiceHuntMessage::Disconnected);
+	eventually("a rejoin", || hunt.count(&Action::Join(chan)) == 2).await;
+	hunt.log.refuse(false);
+	eventually("a second rejoin", || hunt.count(&Action::Join(chan)) >= 3).await;
+
+	assert_eq!(hunt.status().await.channel, Some(chan));
+	// Each try leaves first.
+	assert_eq!(
+		hunt.count(&Action::Leave),
+		hunt.count(&Action::Join(chan)) - 1
+	);
+	assert_eq!(hunt.lost.load(Ordering::Relaxed), 0);
+	// Playback carries on where it was.
+	assert!(!intro.is_done());
+	assert_eq!(hunt.plays().len(), 1);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_backoff() {
+	let hunt = Hunt::start();
+	let chan = ChannelId::new(10);
+
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	let intro = hunt.bgm_after(None).await;
+	hunt.log.refuse(true);
+	hunt.send(VoiceHuntMessage::Disconnected);
+
+	eventually("Felyne to give up", || {
+		hunt.lost.load(Ordering::Relaxed) == 1
+	})
+	.await;
+
+	assert_eq!(hunt.status().await.channel, None);
+	assert_eq!(hunt.count(&Action::Join(chan)), 4);
+	assert_eq!(hunt.log.actions().last(), Some(&Action::Leave));
+	assert!(intro.is_done());
+
+	// The hunt then sends her back wherever she ought to be.
+	hunt.log.refuse(false);
+	hunt.send(VoiceHuntMessage::Channel(chan, 2));
+	hunt.bgm_after(Some(intro.id())).await;
+	assert_eq!(hunt.status().await.channel, Some(chan));
+}
+
+#[tokio::test]
+async fn test_cart_while_dropped_skips_outro() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	hunt.bgm_after(None).await;
+	hunt.log.refuse(true);
+	hunt.send(VoiceHuntMessage::Disconnected);
+	hunt.send(VoiceHuntMessage::Cart);
+
+	assert!(hunt.is_done().await);
+	assert!(!hunt.plays().contains(&OUTRO.to_string()));
+}
+
+#[tokio::test]
+async fn test_failed_track_is_replaced_from_its_state() {
+	let hunt = Hunt::start();
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let intro = hunt.bgm_after(None).await;
+	intro.fail().await;
+
+	let next = hunt.bgm_after(Some(intro.id())).await;
+
+	assert!(INTRO.contains(&next.file().as_str()));
+	assert_ne!(next.file(), intro.file());
+	assert!(hunt.resources.get(&intro.file()).unwrap().is_bad());
+	assert_eq!(hunt.status().await.track_errors, 1);
+}
+
+#[tokio::test]
+async fn test_failing_file_is_quarantined() {
+	let hunt = Hunt::start();
+	let file = "bgm/5815.opus";
+
+	hunt.send(VoiceHuntMessage::Channel(ChannelId::new(10), 2));
+	let mut curr = hunt.bgm_after(None).await;
+
+	for _ in 0..TRACK_QUARANTINE_FAILURES {
+		hunt.send(VoiceHuntMessage::QueueBgm(
+			file.to_string(),
+			VolumeRange::default(),
+		));
+		hunt.send(VoiceHuntMessage::Skip);
+
+		let broken = hunt.bgm_after(Some(curr.id())).await;
+		assert_eq!(broken.file(), file);
+		broken.fail().await;
+
+		// Requested tracks aren't replaced: the machine just moves on.
+		curr = hunt.bgm_after(Some(broken.id())).await;
+		assert_ne!(curr.file(), file);
+	}
+
+	assert!(!hunt.resources.contains_key(file));
+
+	hunt.send(VoiceHuntMessage::QueueBgm(
+		file.to_string(),
+		VolumeRange::default(),
+	));
+	hunt.send(VoiceHuntMessage::Skip);
+	hunt.bgm_after(Some(curr.id())).await;
+
+	let plays = hunt.plays();
+	assert_eq!(
+		plays.iter().filter(|f| *f == file).count() as u32,
+		TRACK_QUARANTINE_FAILURES
+	);
+	assert_eq!(
+		hunt.status().await.track_errors as u32,
+		TRACK_QUARANTINE_FAILURES
+	);
+}